@@ -0,0 +1,394 @@
+// Reads the vocabulary of existing Anki decks, to know which words the user already
+// studies. Three kinds of exports are understood:
+// - the csv this app writes (Vocab, VocabKana, VocabTranslation, ...)
+// - any other text export, e.g. Anki's "Notes in Plain Text", with the columns set in config.json
+// - an .apkg package (legacy collection format), which also has the review state of the cards
+use crate::apkg::FIELD_SEPARATOR;
+use crate::furigana;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::prelude::*;
+
+pub const ANKI_VOCABULARY_FILE: &str = "anki_vocabulary.json";
+
+// newer versions of Anki compress the collection in a way we can't read
+const NO_COLLECTION: &str =
+    "no collection in the package, export it with \"Support older Anki versions\" ticked";
+
+// Where to find the fields of a note, counting from 0, e.g. in config.json:
+// "anki_import": { "file": "my_deck.txt", "word": 1, "reading": 2, "translation": null }
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnkiImport {
+    pub file: String,
+    pub word: usize,
+    pub reading: Option<usize>,
+    pub translation: Option<usize>,
+    // the separator of text exports, by default the one the file uses
+    pub delimiter: Option<char>,
+}
+
+impl Default for AnkiImport {
+    fn default() -> Self {
+        AnkiImport {
+            file: "anki_known_words.txt".to_string(),
+            word: 0,
+            reading: Some(1),
+            translation: Some(2),
+            delimiter: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewState {
+    // text exports don't say
+    Unknown,
+    New,
+    Learning,
+    Review,
+    Suspended,
+}
+
+impl std::fmt::Display for ReviewState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            ReviewState::Unknown => "in a deck",
+            ReviewState::New => "new",
+            ReviewState::Learning => "learning",
+            ReviewState::Review => "in review",
+            ReviewState::Suspended => "suspended",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnkiWord {
+    pub reading: String,
+    pub translation: String,
+    pub state: ReviewState,
+    // days until the next review, 0 for cards that haven't graduated
+    #[serde(default)]
+    pub interval: u32,
+}
+
+impl AnkiWord {
+    // Words that were only added to a deck, or are still being learned, aren't known yet.
+    pub fn is_known(&self) -> bool {
+        match self.state {
+            ReviewState::Unknown | ReviewState::Review => true,
+            ReviewState::Suspended => self.interval > 0,
+            ReviewState::New | ReviewState::Learning => false,
+        }
+    }
+}
+
+// Every word in the user's decks.
+pub type AnkiVocabulary = BTreeMap<String, AnkiWord>;
+
+pub fn load_vocabulary() -> AnkiVocabulary {
+    std::fs::read_to_string(ANKI_VOCABULARY_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn store_vocabulary(vocabulary: &AnkiVocabulary) -> Result<(), Box<dyn Error>> {
+    let mut file = std::fs::File::create(ANKI_VOCABULARY_FILE)?;
+    file.write_all(serde_json::to_string_pretty(vocabulary)?.as_bytes())?;
+    Ok(())
+}
+
+fn strip_html(text: &str) -> String {
+    let mut plain = String::new();
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+    plain.replace("&nbsp;", " ")
+}
+
+// A field without html or furigana.
+fn clean_field(field: &str) -> String {
+    furigana::strip_anki_furigana(&strip_html(field))
+        .trim()
+        .to_string()
+}
+
+fn note_word(
+    fields: &[&str],
+    columns: &AnkiImport,
+    state: ReviewState,
+    interval: u32,
+) -> Option<(String, AnkiWord)> {
+    let field = |column: Option<usize>| {
+        column
+            .and_then(|column| fields.get(column))
+            .map(|field| clean_field(field))
+            .unwrap_or_default()
+    };
+    let word = field(Some(columns.word));
+    if word.is_empty() {
+        return None;
+    }
+    Some((
+        word,
+        AnkiWord {
+            reading: field(columns.reading),
+            translation: field(columns.translation),
+            state,
+            interval,
+        },
+    ))
+}
+
+// Lines starting with # are the headers of Anki's text exports.
+pub fn parse_text_export(
+    text: &str,
+    columns: &AnkiImport,
+    delimiter: char,
+) -> Result<AnkiVocabulary, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter as u8)
+        .comment(Some(b'#'))
+        .from_reader(text.as_bytes());
+    let mut vocabulary = AnkiVocabulary::new();
+    for record in reader.records() {
+        let record = record?;
+        let fields: Vec<&str> = record.iter().collect();
+        vocabulary.extend(note_word(&fields, columns, ReviewState::Unknown, 0));
+    }
+    Ok(vocabulary)
+}
+
+// The notes of a collection with the state of their furthest card.
+pub fn read_collection(
+    connection: &Connection,
+    columns: &AnkiImport,
+) -> Result<AnkiVocabulary, Box<dyn Error>> {
+    let mut statement = connection.prepare(
+        "SELECT notes.flds, cards.type, cards.queue, cards.ivl
+         FROM cards JOIN notes ON cards.nid = notes.id
+         ORDER BY cards.ivl",
+    )?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+    let mut vocabulary = AnkiVocabulary::new();
+    for row in rows {
+        let (flds, card_type, queue, interval) = row?;
+        // queue -1 is suspended, card types are 0 new, 1 learning, 2 review, 3 relearning
+        let state = match (queue, card_type) {
+            (-1, _) => ReviewState::Suspended,
+            (_, 0) => ReviewState::New,
+            (_, 2) => ReviewState::Review,
+            _ => ReviewState::Learning,
+        };
+        // negative intervals are in seconds, for cards in learning
+        let interval = interval.max(0) as u32;
+        let fields: Vec<&str> = flds.split(FIELD_SEPARATOR).collect();
+        // ordered by interval, so the last card of a note is the one furthest along
+        vocabulary.extend(note_word(&fields, columns, state, interval));
+    }
+    Ok(vocabulary)
+}
+
+pub fn read_apkg(path: &str, columns: &AnkiImport) -> Result<AnkiVocabulary, Box<dyn Error>> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    let name = ["collection.anki21", "collection.anki2"]
+        .into_iter()
+        .find(|name| archive.by_name(name).is_ok())
+        .ok_or(NO_COLLECTION)?;
+    let mut collection = Vec::new();
+    archive.by_name(name)?.read_to_end(&mut collection)?;
+
+    // sqlite needs a file to open
+    let collection_path =
+        std::env::temp_dir().join(format!("async-dict-import-{}.anki2", std::process::id()));
+    std::fs::write(&collection_path, collection)?;
+    let vocabulary = Connection::open(&collection_path)
+        .map_err(|error| error.into())
+        .and_then(|connection| read_collection(&connection, columns));
+    std::fs::remove_file(&collection_path)?;
+    vocabulary
+}
+
+// The separator of a text export: the one named by Anki's #separator: header, otherwise
+// the first of tab, comma and semicolon in the first note. The extension doesn't tell,
+// the csv this app exports is a .txt file.
+fn sniff_delimiter(text: &str) -> char {
+    for line in text.lines() {
+        let line = line.trim_start_matches('\u{feff}');
+        if let Some(separator) = line.strip_prefix("#separator:") {
+            let named = match separator.trim().to_lowercase().as_str() {
+                "tab" => Some('\t'),
+                "comma" => Some(','),
+                "semicolon" => Some(';'),
+                "space" => Some(' '),
+                "pipe" => Some('|'),
+                other => other.chars().next(),
+            };
+            if let Some(delimiter) = named {
+                return delimiter;
+            }
+        }
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        return ['\t', ',', ';']
+            .into_iter()
+            .find(|&delimiter| line.contains(delimiter))
+            .unwrap_or('\t');
+    }
+    '\t'
+}
+
+// Reads the configured export, .apkg or text depending on its extension.
+pub fn import(columns: &AnkiImport) -> Result<AnkiVocabulary, Box<dyn Error>> {
+    let path = std::path::Path::new(&columns.file);
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if extension == "apkg" {
+        return read_apkg(&columns.file, columns);
+    }
+    let text = std::fs::read_to_string(path)?;
+    let delimiter = columns.delimiter.unwrap_or_else(|| sniff_delimiter(&text));
+    parse_text_export(&text, columns, delimiter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apkg;
+    use crate::flashcards::{self, Flashcard};
+
+    #[test]
+    fn test_parse_text_export() {
+        let export =
+            "#separator:tab\n#html:true\n猫\tねこ\tcat\n<b>犬</b>\tいぬ\tdog\n見[み]る\tみる\n\n";
+        let vocabulary = parse_text_export(export, &AnkiImport::default(), '\t').unwrap();
+        assert_eq!(
+            vocabulary.keys().collect::<Vec<_>>(),
+            vec!["犬", "猫", "見る"]
+        );
+        assert_eq!(vocabulary["犬"].translation, "dog");
+        assert_eq!(vocabulary["見る"].translation, "");
+        assert!(vocabulary["猫"].is_known());
+    }
+
+    #[test]
+    fn test_parse_csv_with_other_columns() {
+        let export = "\"ねこ, ネコ\",猫\nいぬ,犬\n";
+        let columns = AnkiImport {
+            word: 1,
+            reading: Some(0),
+            translation: None,
+            ..AnkiImport::default()
+        };
+        let vocabulary = parse_text_export(export, &columns, ',').unwrap();
+        assert_eq!(vocabulary["猫"].reading, "ねこ, ネコ");
+        assert_eq!(vocabulary.len(), 2);
+    }
+
+    #[test]
+    fn test_sniff_delimiter() {
+        assert_eq!(
+            sniff_delimiter("#separator:Comma\n#html:false\n猫\tねこ\n"),
+            ','
+        );
+        assert_eq!(
+            sniff_delimiter("#html:true\n\n猫\tねこ\tcat, feline\n"),
+            '\t'
+        );
+        assert_eq!(sniff_delimiter("猫,ねこ,\"cat; feline\"\n"), ',');
+        assert_eq!(sniff_delimiter("猫\n"), '\t');
+    }
+
+    #[test]
+    fn test_import_own_export() {
+        let card = |vocab: &str, translation: &str| Flashcard {
+            vocab: vocab.to_string(),
+            vocab_kana: "ねこ".to_string(),
+            vocab_translation: translation.to_string(),
+            sentence: "猫[ねこ]が好[す]きです。".to_string(),
+            ..Flashcard::default()
+        };
+        // named like the real export, which isn't a .csv
+        let path = std::env::temp_dir().join(format!(
+            "async-dict-test-{}-{}",
+            std::process::id(),
+            flashcards::CSV_EXPORT_FILE
+        ));
+        let path = path.to_str().unwrap();
+        flashcards::append_csv(path, &[card("猫", "cat, feline"), card("子猫", "kitten")]).unwrap();
+        let columns = AnkiImport {
+            file: path.to_string(),
+            ..AnkiImport::default()
+        };
+        let vocabulary = import(&columns);
+        std::fs::remove_file(path).unwrap();
+        let vocabulary = vocabulary.unwrap();
+        assert_eq!(vocabulary.keys().collect::<Vec<_>>(), vec!["子猫", "猫"]);
+        assert_eq!(vocabulary["猫"].reading, "ねこ");
+        assert_eq!(vocabulary["猫"].translation, "cat, feline");
+    }
+
+    #[test]
+    fn test_read_apkg() {
+        let card = |vocab: &str| Flashcard {
+            vocab: vocab.to_string(),
+            vocab_kana: "ねこ".to_string(),
+            vocab_translation: "cat".to_string(),
+            ..Flashcard::default()
+        };
+        let path =
+            std::env::temp_dir().join(format!("async-dict-test-{}.apkg", std::process::id()));
+        let path = path.to_str().unwrap();
+        apkg::write_apkg(path, &[card("猫"), card("犬")], &[]).unwrap();
+        let vocabulary = read_apkg(path, &AnkiImport::default()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(vocabulary.len(), 2);
+        assert_eq!(vocabulary["猫"].state, ReviewState::New);
+        assert_eq!(vocabulary["猫"].reading, "ねこ");
+        assert!(!vocabulary["猫"].is_known());
+    }
+
+    #[test]
+    fn test_review_state() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(apkg::SCHEMA).unwrap();
+        connection
+            .execute_batch(
+                "INSERT INTO notes VALUES (1, 'a', 0, 0, 0, '', '猫\x1fねこ', '猫', 0, 0, '');
+                 INSERT INTO notes VALUES (2, 'b', 0, 0, 0, '', '犬\x1fいぬ', '犬', 0, 0, '');
+                 INSERT INTO cards VALUES (1, 1, 0, 0, 0, 0, 2, 2, 0, 21, 2500, 5, 0, 0, 0, 0, 0, '');
+                 INSERT INTO cards VALUES (2, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, '');
+                 INSERT INTO cards VALUES (3, 2, 0, 0, 0, 0, 1, -1, 0, -600, 0, 1, 0, 0, 0, 0, 0, '');",
+            )
+            .unwrap();
+        let vocabulary = read_collection(&connection, &AnkiImport::default()).unwrap();
+        assert_eq!(vocabulary["猫"].state, ReviewState::Review);
+        assert_eq!(vocabulary["猫"].interval, 21);
+        assert!(vocabulary["猫"].is_known());
+        assert_eq!(vocabulary["犬"].state, ReviewState::Suspended);
+        assert!(!vocabulary["犬"].is_known());
+    }
+}
@@ -0,0 +1,312 @@
+// Writes Anki package (.apkg) files: a zip archive holding a "collection.anki2"
+// sqlite database (legacy schema 11) and a "media" json manifest.
+// Format notes: https://github.com/ankidroid/Anki-Android/wiki/Database-Structure
+use crate::flashcards::{Flashcard, ANKI_DECK_NAME, ANKI_FIELD_NAMES, ANKI_NOTE_TYPE};
+use rusqlite::{params, Connection};
+use serde_json::json;
+use std::error::Error;
+use std::io::prelude::*;
+
+// Anki separates the fields of a note with the unit separator character.
+pub const FIELD_SEPARATOR: char = '\x1f';
+
+// Fixed ids so that re-importing a package updates the same note type and deck.
+const MODEL_ID: i64 = 1_672_531_200_000;
+const DECK_ID: i64 = 1_672_531_200_001;
+
+pub const SCHEMA: &str = "
+CREATE TABLE col (id integer primary key, crt integer not null, mod integer not null,
+    scm integer not null, ver integer not null, dty integer not null, usn integer not null,
+    ls integer not null, conf text not null, models text not null, decks text not null,
+    dconf text not null, tags text not null);
+CREATE TABLE notes (id integer primary key, guid text not null, mid integer not null,
+    mod integer not null, usn integer not null, tags text not null, flds text not null,
+    sfld integer not null, csum integer not null, flags integer not null, data text not null);
+CREATE TABLE cards (id integer primary key, nid integer not null, did integer not null,
+    ord integer not null, mod integer not null, usn integer not null, type integer not null,
+    queue integer not null, due integer not null, ivl integer not null, factor integer not null,
+    reps integer not null, lapses integer not null, left integer not null, odue integer not null,
+    odid integer not null, flags integer not null, data text not null);
+CREATE TABLE revlog (id integer primary key, cid integer not null, usn integer not null,
+    ivl integer not null, lastIvl integer not null, factor integer not null, time integer not null,
+    type integer not null);
+CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null);
+";
+
+const CARD_FRONT: &str =
+    "<div class=vocab>{{Vocab}}</div><div class=sentence>{{furigana:Sentence}}</div>";
+const CARD_BACK: &str = "{{FrontSide}}<hr id=answer>\
+<div class=kana>{{VocabKana}}</div>\
+<div>{{VocabTranslation}}</div>\
+<div class=pos>{{PartOfSpeech}}</div>\
+<div>{{SentenceTranslation}}</div>\
+<div class=notes>{{Notes}}</div>";
+const CARD_CSS: &str =
+    ".card { font-family: Meiryo, sans-serif; font-size: 24px; text-align: center; }\n\
+.vocab { font-size: 48px; }\n.pos, .notes { font-size: 16px; color: grey; }";
+
+// A file the cards refer to by name, e.g. in an <img src="...">.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaFile {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+pub fn write_apkg(
+    path: &str,
+    cards: &[Flashcard],
+    media: &[MediaFile],
+) -> Result<(), Box<dyn Error>> {
+    let now_millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis() as i64;
+    let now = now_millis / 1000;
+
+    let collection_path = std::env::temp_dir().join(format!("async-dict-{}.anki2", now_millis));
+    let _ = std::fs::remove_file(&collection_path);
+    {
+        let connection = Connection::open(&collection_path)?;
+        connection.execute_batch(SCHEMA)?;
+        connection.execute(
+            "INSERT INTO col VALUES (1, ?1, ?2, ?2, 11, 0, 0, 0, ?3, ?4, ?5, ?6, '{}')",
+            params![
+                now,
+                now_millis,
+                collection_config().to_string(),
+                note_types(now).to_string(),
+                decks(now).to_string(),
+                deck_configs(now).to_string(),
+            ],
+        )?;
+
+        for (n, card) in cards.iter().enumerate() {
+            let note_id = now_millis + n as i64;
+            let fields = card.fields();
+            let flds = fields.join(&FIELD_SEPARATOR.to_string());
+            connection.execute(
+                "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, ' async-dict ', ?5, ?6, ?7, 0, '')",
+                params![
+                    note_id,
+                    note_guid(&flds),
+                    MODEL_ID,
+                    now,
+                    flds,
+                    fields[0],
+                    field_checksum(fields[0]),
+                ],
+            )?;
+            // new card (type 0, queue 0), due is the position in the new card queue
+            connection.execute(
+                "INSERT INTO cards VALUES (?1, ?1, ?2, 0, ?3, -1, 0, 0, ?4, 0, 0, 0, 0, 0, 0, 0, 0, '')",
+                params![note_id, DECK_ID, now, n as i64],
+            )?;
+        }
+    }
+
+    let file = std::fs::File::create(path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default();
+    zip.start_file("collection.anki2", options)?;
+    zip.write_all(&std::fs::read(&collection_path)?)?;
+    // media files are stored as "0", "1", ... and the manifest maps them to their names
+    for (n, file) in media.iter().enumerate() {
+        zip.start_file(n.to_string(), options)?;
+        zip.write_all(&file.data)?;
+    }
+    zip.start_file("media", options)?;
+    zip.write_all(media_manifest(media).to_string().as_bytes())?;
+    zip.finish()?;
+    std::fs::remove_file(&collection_path)?;
+    Ok(())
+}
+
+fn media_manifest(media: &[MediaFile]) -> serde_json::Value {
+    let manifest: serde_json::Map<String, serde_json::Value> = media
+        .iter()
+        .enumerate()
+        .map(|(n, file)| (n.to_string(), json!(file.name)))
+        .collect();
+    serde_json::Value::Object(manifest)
+}
+
+// Anki uses the first 8 hex digits of the sha1 of the sort field to spot duplicates.
+pub fn field_checksum(field: &str) -> i64 {
+    let digest = sha1_smol::Sha1::from(field).digest().to_string();
+    i64::from_str_radix(&digest[..8], 16).unwrap_or_default()
+}
+
+// Derived from the fields, so exporting the same card twice doesn't create a second note.
+fn note_guid(flds: &str) -> String {
+    sha1_smol::Sha1::from(flds).digest().to_string()[..10].to_string()
+}
+
+fn collection_config() -> serde_json::Value {
+    json!({
+        "nextPos": 1,
+        "estTimes": true,
+        "activeDecks": [1],
+        "sortType": "noteFld",
+        "timeLim": 0,
+        "sortBackwards": false,
+        "addToCur": true,
+        "curDeck": 1,
+        "newBury": true,
+        "newSpread": 0,
+        "dueCounts": true,
+        "curModel": MODEL_ID.to_string(),
+        "collapseTime": 1200,
+    })
+}
+
+fn note_types(now: i64) -> serde_json::Value {
+    let fields: Vec<serde_json::Value> = ANKI_FIELD_NAMES
+        .iter()
+        .enumerate()
+        .map(|(ord, name)| {
+            json!({
+                "name": name,
+                "ord": ord,
+                "sticky": false,
+                "rtl": false,
+                "font": "Meiryo",
+                "size": 20,
+                "media": [],
+            })
+        })
+        .collect();
+    json!({
+        MODEL_ID.to_string(): {
+            "id": MODEL_ID,
+            "name": ANKI_NOTE_TYPE,
+            "type": 0,
+            "mod": now,
+            "usn": -1,
+            "sortf": 0,
+            "did": DECK_ID,
+            "tmpls": [{
+                "name": "Card 1",
+                "ord": 0,
+                "qfmt": CARD_FRONT,
+                "afmt": CARD_BACK,
+                "bqfmt": "",
+                "bafmt": "",
+                "did": null,
+            }],
+            "flds": fields,
+            "css": CARD_CSS,
+            "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+            "latexPost": "\\end{document}",
+            "tags": [],
+            "vers": [],
+            "req": [[0, "any", [0]]],
+        }
+    })
+}
+
+fn deck(id: i64, name: &str, now: i64) -> serde_json::Value {
+    json!({
+        "id": id,
+        "name": name,
+        "mod": now,
+        "usn": -1,
+        "lrnToday": [0, 0],
+        "revToday": [0, 0],
+        "newToday": [0, 0],
+        "timeToday": [0, 0],
+        "collapsed": false,
+        "desc": "",
+        "dyn": 0,
+        "conf": 1,
+        "extendNew": 10,
+        "extendRev": 50,
+    })
+}
+
+fn decks(now: i64) -> serde_json::Value {
+    json!({
+        "1": deck(1, "Default", now),
+        DECK_ID.to_string(): deck(DECK_ID, ANKI_DECK_NAME, now),
+    })
+}
+
+fn deck_configs(now: i64) -> serde_json::Value {
+    json!({
+        "1": {
+            "id": 1,
+            "name": "Default",
+            "mod": now,
+            "usn": -1,
+            "maxTaken": 60,
+            "autoplay": true,
+            "timer": 0,
+            "replayq": true,
+            "dyn": false,
+            "new": {
+                "delays": [1, 10],
+                "ints": [1, 4, 7],
+                "initialFactor": 2500,
+                "order": 1,
+                "perDay": 20,
+                "bury": true,
+                "separate": true,
+            },
+            "rev": {
+                "perDay": 100,
+                "ease4": 1.3,
+                "fuzz": 0.05,
+                "ivlFct": 1,
+                "maxIvl": 36500,
+                "minSpace": 1,
+                "bury": true,
+            },
+            "lapse": {
+                "delays": [10],
+                "mult": 0,
+                "minInt": 1,
+                "leechFails": 8,
+                "leechAction": 0,
+            },
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_checksum() {
+        // Anki keeps the first 32 bits of the sha1
+        let digest = sha1_smol::Sha1::from("猫").digest().to_string();
+        assert_eq!(
+            field_checksum("猫"),
+            i64::from_str_radix(&digest[..8], 16).unwrap()
+        );
+        assert_ne!(field_checksum("猫"), field_checksum("犬"));
+    }
+
+    #[test]
+    fn test_media_manifest() {
+        let media = [
+            MediaFile {
+                name: "kanjivg_0732b.svg".to_string(),
+                data: Vec::new(),
+            },
+            MediaFile {
+                name: "kanjivg_04eba.svg".to_string(),
+                data: Vec::new(),
+            },
+        ];
+        assert_eq!(media_manifest(&[]), json!({}));
+        assert_eq!(
+            media_manifest(&media),
+            json!({"0": "kanjivg_0732b.svg", "1": "kanjivg_04eba.svg"})
+        );
+    }
+
+    #[test]
+    fn test_note_guid_is_stable() {
+        assert_eq!(note_guid("猫\x1fねこ"), note_guid("猫\x1fねこ"));
+        assert_eq!(note_guid("猫\x1fねこ").len(), 10);
+    }
+}
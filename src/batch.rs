@@ -0,0 +1,233 @@
+// Cards for a whole list of words at once: every word is looked up, gets a card with a
+// sentence, and whatever needs a second look is flagged before the cards are added.
+use crate::flashcards::Flashcard;
+use crate::SearchResult;
+
+pub const BATCH_WORDS_FILE: &str = "batch_words.txt";
+
+// One word per line. Only the first column of csv or tab separated lines counts, lines
+// starting with # are skipped, and so are words that are on the list already.
+pub fn read_word_list(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let word = line
+            .split(['\t', ',', '、'])
+            .next()
+            .unwrap_or_default()
+            .trim();
+        if !word.is_empty() && !words.iter().any(|known| known == word) {
+            words.push(word.to_string());
+        }
+    }
+    words
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Issue {
+    // jisho couldn't be reached or gave no usable answer
+    LookupFailed,
+    NotFound,
+    // jisho only had words containing it, the closest one was picked
+    NoExactMatch,
+    // e.g. かみ is 紙, 神 and 髪
+    MultipleEntries(usize),
+    NoSentences,
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Issue::LookupFailed => write!(f, "lookup failed"),
+            Issue::NotFound => write!(f, "not found"),
+            Issue::NoExactMatch => write!(f, "no exact match"),
+            Issue::MultipleEntries(count) => write!(f, "{} entries", count),
+            Issue::NoSentences => write!(f, "no sentences"),
+        }
+    }
+}
+
+// The results that are the word itself, in kanji or in kana. Jisho also returns
+// words that merely contain it.
+pub fn exact_matches(word: &str, results: &[SearchResult]) -> Vec<usize> {
+    (0..results.len())
+        .filter(|&i| results[i].japanese == word || results[i].reading == word)
+        .collect()
+}
+
+// The result a card is made of: the first exact match, jisho's best one otherwise.
+pub fn best_match(word: &str, results: &[SearchResult]) -> Option<usize> {
+    if results.is_empty() {
+        return None;
+    }
+    Some(exact_matches(word, results).first().copied().unwrap_or(0))
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchEntry {
+    pub word: String,
+    // None while it's being looked up
+    pub results: Option<Vec<SearchResult>>,
+    // the lookup went wrong, the word has no results because of that
+    pub failed: bool,
+    pub chosen: usize,
+    pub card: Option<Flashcard>,
+    // how many example sentences the chosen word has
+    pub sentences: usize,
+    // the sentence the word was found in, used instead of an example sentence
+    pub context: Option<String>,
+}
+
+impl BatchEntry {
+    pub fn new(word: String) -> Self {
+        BatchEntry {
+            word,
+            results: None,
+            failed: false,
+            chosen: 0,
+            card: None,
+            sentences: 0,
+            context: None,
+        }
+    }
+
+    // Takes jisho's answer, None when the word couldn't be looked up.
+    pub fn set_results(&mut self, results: Option<Vec<SearchResult>>) {
+        self.failed = results.is_none();
+        let results = results.unwrap_or_default();
+        self.chosen = best_match(&self.word, &results).unwrap_or_default();
+        self.results = Some(results);
+    }
+
+    pub fn chosen_result(&self) -> Option<&SearchResult> {
+        self.results.as_ref()?.get(self.chosen)
+    }
+
+    // The result after the chosen one, going through the exact matches if there are
+    // more of them, through all results otherwise.
+    pub fn next_choice(&self) -> Option<usize> {
+        let results = self.results.as_ref()?;
+        let exact = exact_matches(&self.word, results);
+        let choices: Vec<usize> = if exact.len() > 1 {
+            exact
+        } else {
+            (0..results.len()).collect()
+        };
+        let current = choices.iter().position(|&i| i == self.chosen);
+        let next = current
+            .map(|current| (current + 1) % choices.len())
+            .unwrap_or(0);
+        choices.get(next).copied()
+    }
+
+    pub fn issues(&self) -> Vec<Issue> {
+        let Some(results) = &self.results else {
+            return Vec::new();
+        };
+        if self.failed {
+            return vec![Issue::LookupFailed];
+        }
+        if results.is_empty() {
+            return vec![Issue::NotFound];
+        }
+        let mut issues = Vec::new();
+        match exact_matches(&self.word, results).len() {
+            0 => issues.push(Issue::NoExactMatch),
+            1 => {}
+            count => issues.push(Issue::MultipleEntries(count)),
+        }
+        if self.sentences == 0 && self.context.is_none() {
+            issues.push(Issue::NoSentences);
+        }
+        issues
+    }
+}
+
+// How far the lookups are, e.g. "12/200 looked up, 3 need a look".
+pub fn progress(entries: &[BatchEntry]) -> String {
+    let looked_up = entries
+        .iter()
+        .filter(|entry| entry.results.is_some())
+        .count();
+    let flagged = entries
+        .iter()
+        .filter(|entry| !entry.issues().is_empty())
+        .count();
+    format!(
+        "{}/{} looked up, {} need a look",
+        looked_up,
+        entries.len(),
+        flagged
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranking::Ranking;
+
+    fn result(japanese: &str, reading: &str) -> SearchResult {
+        SearchResult::new(
+            japanese.to_string(),
+            reading.to_string(),
+            vec!["translation".to_string()],
+            Ranking::default(),
+        )
+    }
+
+    #[test]
+    fn test_read_word_list() {
+        let list = "\u{feff}# chapter 1\n猫\n 犬 \n\n猫,ねこ,cat\n食べる\tたべる\n";
+        assert_eq!(read_word_list(list), vec!["猫", "犬", "食べる"]);
+    }
+
+    #[test]
+    fn test_best_match() {
+        let results = vec![result("猫舌", "ねこじた"), result("猫", "ねこ")];
+        assert_eq!(best_match("猫", &results), Some(1));
+        assert_eq!(best_match("ねこ", &results), Some(1));
+        assert_eq!(best_match("犬", &results), Some(0));
+        assert_eq!(best_match("犬", &[]), None);
+    }
+
+    #[test]
+    fn test_issues() {
+        let mut entry = BatchEntry::new("かみ".to_string());
+        assert!(entry.issues().is_empty());
+        entry.results = Some(vec![
+            result("紙", "かみ"),
+            result("上", "うえ"),
+            result("神", "かみ"),
+        ]);
+        entry.sentences = 4;
+        assert_eq!(entry.issues(), vec![Issue::MultipleEntries(2)]);
+        assert_eq!(entry.next_choice(), Some(2));
+        entry.chosen = 2;
+        assert_eq!(entry.next_choice(), Some(0));
+
+        entry.word = "かみさま".to_string();
+        entry.sentences = 0;
+        assert_eq!(
+            entry.issues(),
+            vec![Issue::NoExactMatch, Issue::NoSentences]
+        );
+        entry.context = Some("かみさまが来た。".to_string());
+        assert_eq!(entry.issues(), vec![Issue::NoExactMatch]);
+        entry.results = Some(vec![]);
+        assert_eq!(entry.issues(), vec![Issue::NotFound]);
+        assert_eq!(progress(&[entry]), "1/1 looked up, 1 need a look");
+    }
+
+    #[test]
+    fn test_failed_lookup() {
+        let mut entry = BatchEntry::new("猫".to_string());
+        entry.set_results(None);
+        assert_eq!(entry.issues(), vec![Issue::LookupFailed]);
+        entry.set_results(Some(vec![result("猫舌", "ねこじた"), result("猫", "ねこ")]));
+        assert_eq!(entry.chosen, 1);
+        assert_eq!(entry.issues(), vec![Issue::NoSentences]);
+    }
+}
@@ -0,0 +1,485 @@
+// The command line version, for scripting: `async-dict search 猫 犬 --format tsv`.
+// It works on the same files as the application, e.g. cards added here show up in
+// its pending cards.
+use crate::batch::{self, BatchEntry, Issue};
+use crate::difficulty::SentencePolicy;
+use crate::flashcards::Flashcard;
+use crate::{apkg, config, flashcards, kanjivg, known_words, srs};
+use crate::{Data, Dict, DictError, SearchResult};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::json;
+use std::error::Error;
+use std::io::BufRead;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "async-dict",
+    about = "Look up Japanese words and make Anki flash cards. Without a command the application opens."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    #[arg(long, short, value_enum, default_value_t = Format::Plain, global = true)]
+    format: Format,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Look words up on jisho. Without words they're read from stdin, one per line.
+    Search { words: Vec<String> },
+    /// Search the example sentences, in Japanese or English
+    Sentences {
+        query: String,
+        #[arg(long, short, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Add or list pending flash cards
+    #[command(subcommand)]
+    Card(CardCommand),
+    /// Export the pending flash cards
+    Export {
+        #[arg(value_enum)]
+        to: ExportTarget,
+        /// Add stroke order diagrams to .apkg cards
+        #[arg(long)]
+        stroke_order: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CardCommand {
+    /// Look words up and add a card for each. Without words they're read from stdin.
+    Add {
+        words: Vec<String>,
+    },
+    /// Make cards for every word of a list, one word per line, and show what needs a look
+    Batch {
+        file: String,
+        /// Add the cards to the pending cards instead of only showing them
+        #[arg(long)]
+        add: bool,
+    },
+    List,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportTarget {
+    Csv,
+    Apkg,
+    AnkiConnect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Plain,
+    Json,
+    Tsv,
+}
+
+// Kana and kanji take up two columns in a terminal.
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c >= '\u{1100}' { 2 } else { 1 })
+        .sum()
+}
+
+// A table of strings, printed in the requested format.
+struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn new(headers: &[&'static str]) -> Self {
+        Table {
+            headers: headers.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn format(&self, format: Format) -> String {
+        match format {
+            // an array of objects keyed by the headers
+            Format::Json => {
+                let rows: Vec<serde_json::Value> = self
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        self.headers
+                            .iter()
+                            .zip(row)
+                            .map(|(header, value)| (header.to_string(), json!(value)))
+                            .collect::<serde_json::Map<_, _>>()
+                            .into()
+                    })
+                    .collect();
+                serde_json::to_string_pretty(&rows).unwrap_or_default() + "\n"
+            }
+            // tabs and newlines in values would break the columns
+            Format::Tsv => std::iter::once(self.headers.iter().map(|h| h.to_string()).collect())
+                .chain(self.rows.iter().cloned())
+                .map(|row: Vec<String>| {
+                    let row: Vec<String> = row
+                        .iter()
+                        .map(|value| value.replace(['\t', '\n'], " "))
+                        .collect();
+                    row.join("\t") + "\n"
+                })
+                .collect(),
+            // columns padded to the same width, no headers
+            Format::Plain => {
+                let widths: Vec<usize> = (0..self.headers.len())
+                    .map(|column| {
+                        self.rows
+                            .iter()
+                            .map(|row| display_width(&row[column]))
+                            .max()
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                self.rows
+                    .iter()
+                    .map(|row| {
+                        let padded: Vec<String> = row
+                            .iter()
+                            .zip(&widths)
+                            .map(|(value, width)| {
+                                let padding = width - display_width(value);
+                                format!("{}{}", value, " ".repeat(padding))
+                            })
+                            .collect();
+                        padded.join("  ").trim_end().to_string() + "\n"
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn search_table(results: &[(String, SearchResult)]) -> Table {
+    let mut table = Table::new(&[
+        "query",
+        "word",
+        "reading",
+        "translations",
+        "common",
+        "jlpt",
+        "sentences",
+    ]);
+    for (query, result) in results {
+        table.rows.push(vec![
+            query.clone(),
+            result.japanese.clone(),
+            result.reading.clone(),
+            result.translations.join("; "),
+            result.ranking.is_common.to_string(),
+            result
+                .ranking
+                .jlpt
+                .map(|level| format!("N{}", level))
+                .unwrap_or_default(),
+            result.ranking.frequency.to_string(),
+        ]);
+    }
+    table
+}
+
+fn batch_table(entries: &[BatchEntry]) -> Table {
+    let mut table = Table::new(&[
+        "word",
+        "vocab",
+        "vocab_kana",
+        "vocab_translation",
+        "sentence",
+        "sentence_translation",
+        "issues",
+    ]);
+    for entry in entries {
+        let card = entry.card.clone().unwrap_or_default();
+        let issues: Vec<String> = entry.issues().iter().map(Issue::to_string).collect();
+        table.rows.push(vec![
+            entry.word.clone(),
+            card.vocab,
+            card.vocab_kana,
+            card.vocab_translation,
+            card.sentence,
+            card.sentence_translation,
+            issues.join(", "),
+        ]);
+    }
+    table
+}
+
+fn cards_table(cards: &[Flashcard]) -> Table {
+    let mut table = Table::new(&[
+        "vocab",
+        "vocab_kana",
+        "vocab_translation",
+        "sentence",
+        "sentence_translation",
+        "notes",
+    ]);
+    for card in cards {
+        table.rows.push(vec![
+            card.vocab.clone(),
+            card.vocab_kana.clone(),
+            card.vocab_translation.clone(),
+            card.sentence.clone(),
+            card.sentence_translation.clone(),
+            card.notes.clone(),
+        ]);
+    }
+    table
+}
+
+// The given words, or the lines of stdin without them.
+fn words_or_stdin(words: Vec<String>) -> Vec<String> {
+    if !words.is_empty() {
+        return words;
+    }
+    std::io::stdin()
+        .lock()
+        .lines()
+        .map_while(Result::ok)
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+async fn load_data() -> Result<Data, Box<dyn Error>> {
+    let sentences = Dict::load_example_sentences().await.map_err(describe)?;
+    let (example_sentences, sentence_index) = Dict::parse_example_sentences(sentences);
+    let config = config::load_config();
+    // only the easiest sentence needs the kanji, and it makes do without them
+    let kanjidic = match config.sentence_policy {
+        SentencePolicy::Easiest => Dict::load_kanjidic().await.unwrap_or_default(),
+        _ => Default::default(),
+    };
+    Ok(Data {
+        example_sentences,
+        sentence_index,
+        kanjidic,
+        pending_cards: flashcards::load_pending_cards(),
+        study_database: srs::open_database().ok(),
+        known_words: known_words::load_known_words(),
+        sentence_policy: config.sentence_policy,
+        card_furigana: config.card_furigana,
+        ..Data::default()
+    })
+}
+
+fn describe(error: DictError) -> Box<dyn Error> {
+    match error {
+        DictError::AnkiConnect(error) => format!("AnkiConnect refused the cards: {}", error),
+        error => format!("{:?}", error),
+    }
+    .into()
+}
+
+// Every jisho result of each word, with the word it was found for.
+async fn look_up(
+    data: &Data,
+    words: &[String],
+) -> Result<Vec<(String, SearchResult)>, Box<dyn Error>> {
+    let mut results = Vec::new();
+    for word in words {
+        let response = Dict::search(word.clone()).await.map_err(describe)?;
+        for result in crate::search_results(&response, &data.example_sentences) {
+            results.push((word.clone(), result));
+        }
+    }
+    Ok(results)
+}
+
+// Looks the words up one by one and gives each a card, the way the batch screen does.
+// A word that couldn't be looked up is reported and flagged.
+async fn make_cards(data: &mut Data, words: Vec<String>) {
+    data.batch = words.into_iter().map(BatchEntry::new).collect();
+    for index in 0..data.batch.len() {
+        let word = data.batch[index].word.clone();
+        let results = match Dict::search(word.clone()).await {
+            Ok(response) => Some(crate::search_results(&response, &data.example_sentences)),
+            Err(error) => {
+                eprintln!("{}: {}", word, describe(error));
+                None
+            }
+        };
+        data.batch[index].set_results(results);
+        crate::fill_batch_card(data, index);
+    }
+}
+
+// Adds the cards of the batch to the pending cards, returns them.
+fn add_cards(data: &mut Data) -> Result<Vec<Flashcard>, Box<dyn Error>> {
+    let cards: Vec<Flashcard> = data
+        .batch
+        .iter()
+        .filter_map(|entry| entry.card.clone())
+        .collect();
+    for card in &cards {
+        let database = data.study_database.as_ref();
+        crate::save_flashcard(&mut data.pending_cards, database, card.clone());
+    }
+    flashcards::store_pending_cards(&data.pending_cards)?;
+    Ok(cards)
+}
+
+async fn run_command(cli: Cli) -> Result<String, Box<dyn Error>> {
+    match cli.command {
+        Command::Search { words } => {
+            let data = load_data().await?;
+            let results = look_up(&data, &words_or_stdin(words)).await?;
+            Ok(search_table(&results).format(cli.format))
+        }
+        Command::Sentences { query, limit } => {
+            let data = load_data().await?;
+            let mut table = Table::new(&["id", "japanese", "english"]);
+            for found in data.sentence_index.search(&query, limit) {
+                if let Some(sentence) = data.sentence_index.sentence(found.id) {
+                    table.rows.push(vec![
+                        sentence.japanese_sentence_id.to_string(),
+                        sentence.japanese_text.clone(),
+                        sentence.english_text.clone(),
+                    ]);
+                }
+            }
+            Ok(table.format(cli.format))
+        }
+        Command::Card(CardCommand::Add { words }) => {
+            let mut data = load_data().await?;
+            make_cards(&mut data, words_or_stdin(words)).await;
+            for entry in &data.batch {
+                if entry.card.is_none() {
+                    eprintln!("{}: not found", entry.word);
+                }
+            }
+            let added = add_cards(&mut data)?;
+            Ok(cards_table(&added).format(cli.format))
+        }
+        Command::Card(CardCommand::Batch { file, add }) => {
+            let words = batch::read_word_list(&std::fs::read_to_string(&file)?);
+            let mut data = load_data().await?;
+            make_cards(&mut data, words).await;
+            let table = batch_table(&data.batch);
+            if add {
+                add_cards(&mut data)?;
+            }
+            Ok(table.format(cli.format))
+        }
+        Command::Card(CardCommand::List) => {
+            Ok(cards_table(&flashcards::load_pending_cards()).format(cli.format))
+        }
+        Command::Export { to, stroke_order } => {
+            let mut cards = flashcards::load_pending_cards();
+            let (exported, destination) = match to {
+                ExportTarget::Csv => {
+                    flashcards::export_to_csv(&cards)?;
+                    (cards.clone(), flashcards::CSV_EXPORT_FILE)
+                }
+                ExportTarget::Apkg => {
+                    let (with_media, media) = if stroke_order {
+                        kanjivg::with_stroke_order_media(&cards)
+                    } else {
+                        (cards.clone(), Vec::new())
+                    };
+                    apkg::write_apkg(flashcards::APKG_EXPORT_FILE, &with_media, &media)?;
+                    (cards.clone(), flashcards::APKG_EXPORT_FILE)
+                }
+                ExportTarget::AnkiConnect => {
+                    let added = Dict::export_to_anki_connect(cards.clone())
+                        .await
+                        .map_err(describe)?;
+                    (added, "Anki")
+                }
+            };
+            // cards Anki refused stay pending
+            flashcards::remove_exported(&mut cards, &exported);
+            flashcards::store_pending_cards(&cards)?;
+            let mut report = format!("Exported {} card(s) to {}\n", exported.len(), destination);
+            if !cards.is_empty() {
+                report += &format!(
+                    "{} card(s) were refused and are still pending\n",
+                    cards.len()
+                );
+            }
+            Ok(report)
+        }
+    }
+}
+
+// Runs the command given on the command line, returns the exit code.
+pub fn run() -> i32 {
+    let cli = Cli::parse();
+    let result = tokio::runtime::Runtime::new()
+        .map_err(|error| error.into())
+        .and_then(|runtime| runtime.block_on(run_command(cli)));
+    match result {
+        Ok(output) => {
+            print!("{}", output);
+            0
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let mut table = Table::new(&["word", "reading"]);
+        table.rows.push(vec!["猫".to_string(), "ねこ".to_string()]);
+        table
+            .rows
+            .push(vec!["日本語".to_string(), "に\tほんご".to_string()]);
+        table
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let cli = Cli::try_parse_from(["async-dict", "search", "猫", "犬", "-f", "tsv"]).unwrap();
+        assert_eq!(cli.format, Format::Tsv);
+        assert!(matches!(cli.command, Command::Search { words } if words == ["猫", "犬"]));
+
+        let cli = Cli::try_parse_from(["async-dict", "card", "add"]).unwrap();
+        assert!(
+            matches!(cli.command, Command::Card(CardCommand::Add { words }) if words.is_empty())
+        );
+
+        let cli =
+            Cli::try_parse_from(["async-dict", "card", "batch", "words.txt", "--add"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Card(CardCommand::Batch { file, add: true }) if file == "words.txt"
+        ));
+
+        let cli = Cli::try_parse_from(["async-dict", "export", "anki-connect"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Export {
+                to: ExportTarget::AnkiConnect,
+                stroke_order: false
+            }
+        ));
+        assert!(Cli::try_parse_from(["async-dict", "export", "pdf"]).is_err());
+    }
+
+    #[test]
+    fn test_formats() {
+        let table = table();
+        assert_eq!(
+            table.format(Format::Tsv),
+            "word\treading\n猫\tねこ\n日本語\tに ほんご\n"
+        );
+        assert_eq!(
+            table.format(Format::Plain),
+            "猫      ねこ\n日本語  に\tほんご\n"
+        );
+        let json: serde_json::Value = serde_json::from_str(&table.format(Format::Json)).unwrap();
+        assert_eq!(json[1]["word"], "日本語");
+        assert_eq!(json.as_array().unwrap().len(), 2);
+    }
+}
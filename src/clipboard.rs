@@ -0,0 +1,193 @@
+// Looks up Japanese text as soon as it's copied, for reading in a browser with the
+// app next to it. It's off unless turned on on the search screen or in config.json.
+use crate::sentence_index::is_japanese;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::prelude::*;
+use std::time::{Duration, Instant};
+
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// whether watching was ticked on the search screen, which wins over config.json
+pub const WATCHING_FILE: &str = "clipboard_watching.json";
+// longer selections are sentences rather than something to look up
+const MAX_LOOKUP_LENGTH: usize = 30;
+
+// e.g. "clipboard": { "enabled": true, "seconds_between_lookups": 5, "ignore": ["はい"] }
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    pub enabled: bool,
+    // copies that come quicker are looked up once the time is up, only the last of them
+    pub seconds_between_lookups: u64,
+    // text that's never looked up
+    pub ignore: Vec<String>,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        ClipboardConfig {
+            enabled: false,
+            seconds_between_lookups: 2,
+            ignore: Vec::new(),
+        }
+    }
+}
+
+pub fn load_watching() -> Option<bool> {
+    std::fs::read_to_string(WATCHING_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+}
+
+pub fn store_watching(enabled: bool) -> Result<(), Box<dyn Error>> {
+    let mut file = std::fs::File::create(WATCHING_FILE)?;
+    file.write_all(serde_json::to_string(&enabled)?.as_bytes())?;
+    Ok(())
+}
+
+pub trait Clipboard {
+    // None when the clipboard is empty or holds something other than text
+    fn text(&mut self) -> Option<String>;
+}
+
+// The clipboard of the desktop, X11 or Wayland.
+pub struct SystemClipboard(arboard::Clipboard);
+
+impl SystemClipboard {
+    pub fn new() -> Result<Self, arboard::Error> {
+        arboard::Clipboard::new().map(SystemClipboard)
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn text(&mut self) -> Option<String> {
+        self.0.get_text().ok()
+    }
+}
+
+pub struct ClipboardWatcher<C: Clipboard> {
+    clipboard: C,
+    min_interval: Duration,
+    ignore: Vec<String>,
+    // what the clipboard held last time, only new copies count
+    last_text: Option<String>,
+    // a copy that came too soon after the last lookup
+    waiting: Option<String>,
+    last_lookup: Option<Instant>,
+}
+
+impl<C: Clipboard> ClipboardWatcher<C> {
+    pub fn new(mut clipboard: C, config: &ClipboardConfig) -> Self {
+        // what was copied before watching started isn't looked up
+        let last_text = clipboard.text();
+        ClipboardWatcher {
+            clipboard,
+            min_interval: Duration::from_secs(config.seconds_between_lookups),
+            ignore: config.ignore.clone(),
+            last_text,
+            waiting: None,
+            last_lookup: None,
+        }
+    }
+
+    fn lookup_query(&self, text: &str) -> Option<String> {
+        let text = text.trim();
+        let worth_it = !text.is_empty()
+            && text.chars().count() <= MAX_LOOKUP_LENGTH
+            && text.chars().any(is_japanese)
+            && !self.ignore.iter().any(|ignored| ignored == text);
+        worth_it.then(|| text.to_string())
+    }
+
+    // What to look up now, if something new was copied and the last lookup was long
+    // enough ago.
+    pub fn poll(&mut self, now: Instant) -> Option<String> {
+        let text = self.clipboard.text();
+        if text.is_some() && text != self.last_text {
+            if let Some(query) = text.as_deref().and_then(|text| self.lookup_query(text)) {
+                self.waiting = Some(query);
+            }
+            self.last_text = text;
+        }
+        if self
+            .last_lookup
+            .is_some_and(|last| now.duration_since(last) < self.min_interval)
+        {
+            return None;
+        }
+        let query = self.waiting.take()?;
+        self.last_lookup = Some(now);
+        Some(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // A clipboard the test copies to.
+    #[derive(Clone, Default)]
+    struct FakeClipboard(Rc<RefCell<Option<String>>>);
+
+    impl FakeClipboard {
+        fn copy(&self, text: &str) {
+            *self.0.borrow_mut() = Some(text.to_string());
+        }
+    }
+
+    impl Clipboard for FakeClipboard {
+        fn text(&mut self) -> Option<String> {
+            self.0.borrow().clone()
+        }
+    }
+
+    fn watcher(clipboard: &FakeClipboard) -> ClipboardWatcher<FakeClipboard> {
+        let config = ClipboardConfig {
+            enabled: true,
+            seconds_between_lookups: 2,
+            ignore: vec!["はい".to_string()],
+        };
+        ClipboardWatcher::new(clipboard.clone(), &config)
+    }
+
+    #[test]
+    fn test_new_japanese_text() {
+        let clipboard = FakeClipboard::default();
+        clipboard.copy("猫");
+        let mut watcher = watcher(&clipboard);
+        let start = Instant::now();
+        // copied before watching
+        assert_eq!(watcher.poll(start), None);
+
+        clipboard.copy(" 犬\n");
+        assert_eq!(watcher.poll(start).as_deref(), Some("犬"));
+        assert_eq!(watcher.poll(start + Duration::from_secs(5)), None);
+
+        let later = start + Duration::from_secs(10);
+        for ignored in ["hello", "はい", &"長い".repeat(20)] {
+            clipboard.copy(ignored);
+            assert_eq!(watcher.poll(later), None);
+        }
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let clipboard = FakeClipboard::default();
+        let mut watcher = watcher(&clipboard);
+        let start = Instant::now();
+        clipboard.copy("猫");
+        assert_eq!(watcher.poll(start).as_deref(), Some("猫"));
+        clipboard.copy("犬");
+        assert_eq!(watcher.poll(start + Duration::from_secs(1)), None);
+        clipboard.copy("鳥");
+        assert_eq!(watcher.poll(start + Duration::from_millis(1500)), None);
+        // only the last copy is looked up once the time is up
+        assert_eq!(
+            watcher.poll(start + Duration::from_secs(2)).as_deref(),
+            Some("鳥")
+        );
+        assert_eq!(watcher.poll(start + Duration::from_secs(3)), None);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::anki_import::AnkiImport;
+use crate::clipboard::ClipboardConfig;
+use crate::difficulty::SentencePolicy;
+use crate::keymap::{Action, Screen};
+use crate::romaji::{InputMode, RomajiDisplay};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::prelude::*;
+
+pub const CONFIG_FILE: &str = "config.json";
+
+// User settings, read from config.json next to the executable's working directory.
+// Every field is optional, e.g.
+// { "key_bindings": { "N": "next", "Ctrl+P": "previous" },
+//   "screen_key_bindings": { "study": { "Space": "confirm" } },
+//   "input_mode": "hiragana", "romaji": "hepburn",
+//   "anki_import": { "file": "japanese.apkg", "word": 0 }, "sentence_policy": "easiest",
+//   "card_furigana": true }
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub key_bindings: BTreeMap<String, Action>,
+    pub screen_key_bindings: BTreeMap<Screen, BTreeMap<String, Action>>,
+    // what the search box starts in, and how readings are romanized
+    pub input_mode: InputMode,
+    pub romaji: RomajiDisplay,
+    // which Anki export to read the known vocabulary from
+    pub anki_import: AnkiImport,
+    // the example sentence new cards get: "i_plus_one", "shortest" or "easiest"
+    pub sentence_policy: SentencePolicy,
+    // card sentences with readings in Anki's 漢字[かんじ] format, for {{furigana:Sentence}}
+    pub card_furigana: bool,
+    // looking up copied text, off by default
+    pub clipboard: ClipboardConfig,
+}
+
+pub fn load_config() -> Config {
+    match std::fs::read_to_string(CONFIG_FILE) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|error| {
+            eprintln!("config: could not parse {}: {}", CONFIG_FILE, error);
+            Config::default()
+        }),
+        Err(_) => Config::default(),
+    }
+}
+
+pub const WINDOW_FILE: &str = "window.json";
+
+// Size and position of the window when the app was last closed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    pub width: u32,
+    pub height: u32,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        WindowGeometry {
+            width: 800,
+            height: 600,
+            x: None,
+            y: None,
+        }
+    }
+}
+
+pub fn load_window_geometry() -> WindowGeometry {
+    std::fs::read_to_string(WINDOW_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn store_window_geometry(geometry: &WindowGeometry) -> Result<(), Box<dyn Error>> {
+    let mut file = std::fs::File::create(WINDOW_FILE)?;
+    file.write_all(serde_json::to_string_pretty(geometry)?.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,200 @@
+// Turns inflected verbs and adjectives back into their dictionary form by swapping
+// endings: 食べました -> 食べる, 書かなかった -> 書く, 高くない -> 高い.
+// The rules don't know which word is which kind of verb, so they also produce forms that
+// don't exist; whoever asks checks the candidates against a dictionary.
+
+use std::sync::OnceLock;
+
+// rules are applied to what other rules produced, e.g. 食べたくない -> 食べたい -> 食べる
+const MAX_STEPS: usize = 3;
+
+// The endings of a godan verb: dictionary form, and the a, i, e and o stems, te form
+// and ta form.
+const GODAN: [(&str, &str, &str, &str, &str, &str, &str); 9] = [
+    ("う", "わ", "い", "え", "お", "って", "った"),
+    ("く", "か", "き", "け", "こ", "いて", "いた"),
+    ("ぐ", "が", "ぎ", "げ", "ご", "いで", "いだ"),
+    ("す", "さ", "し", "せ", "そ", "して", "した"),
+    ("つ", "た", "ち", "て", "と", "って", "った"),
+    ("ぬ", "な", "に", "ね", "の", "んで", "んだ"),
+    ("ぶ", "ば", "び", "べ", "ぼ", "んで", "んだ"),
+    ("む", "ま", "み", "め", "も", "んで", "んだ"),
+    ("る", "ら", "り", "れ", "ろ", "って", "った"),
+];
+
+// what follows the i stem (the ます stem), for godan and ichidan verbs alike
+const I_STEM_ENDINGS: [&str; 8] = [
+    "ます",
+    "ました",
+    "ません",
+    "ませんでした",
+    "ましょう",
+    "まして",
+    "たい",
+    "ながら",
+];
+// what follows the a stem of godan verbs, or the stem of ichidan verbs
+const A_STEM_ENDINGS: [&str; 4] = ["ない", "なかった", "ず", "なくて"];
+
+// Endings that aren't made from one of the stems.
+const OTHER_RULES: [(&str, &str); 33] = [
+    // ichidan verbs, whose stem is the dictionary form without る
+    ("て", "る"),
+    ("た", "る"),
+    ("たら", "る"),
+    ("たり", "る"),
+    ("られる", "る"),
+    ("させる", "る"),
+    ("れば", "る"),
+    ("よう", "る"),
+    ("ろ", "る"),
+    // する and 来る
+    ("した", "する"),
+    ("して", "する"),
+    ("したら", "する"),
+    ("しない", "する"),
+    ("しなかった", "する"),
+    ("します", "する"),
+    ("しました", "する"),
+    ("しません", "する"),
+    ("しよう", "する"),
+    ("される", "する"),
+    ("させる", "する"),
+    ("すれば", "する"),
+    ("きた", "くる"),
+    ("きて", "くる"),
+    ("きます", "くる"),
+    ("こない", "くる"),
+    // い adjectives
+    ("くない", "い"),
+    ("かった", "い"),
+    ("くなかった", "い"),
+    ("くて", "い"),
+    ("ければ", "い"),
+    ("く", "い"),
+    ("さ", "い"),
+    // 行く is the one godan verb with an irregular te form
+    ("って", "く"),
+];
+
+// (inflected ending, dictionary ending) pairs, made once
+fn rules() -> &'static [(String, String)] {
+    static RULES: OnceLock<Vec<(String, String)>> = OnceLock::new();
+    RULES.get_or_init(make_rules)
+}
+
+fn make_rules() -> Vec<(String, String)> {
+    let mut rules: Vec<(String, String)> = OTHER_RULES
+        .iter()
+        .map(|&(from, to)| (from.to_string(), to.to_string()))
+        .collect();
+    for ending in I_STEM_ENDINGS.iter().chain(A_STEM_ENDINGS.iter()) {
+        rules.push((ending.to_string(), "る".to_string()));
+    }
+    for (u, a, i, e, o, te, ta) in GODAN {
+        let mut push = |from: String| rules.push((from, u.to_string()));
+        for ending in I_STEM_ENDINGS {
+            push(format!("{}{}", i, ending));
+        }
+        for ending in A_STEM_ENDINGS {
+            push(format!("{}{}", a, ending));
+        }
+        // passive, causative, potential, conditional and volitional
+        push(format!("{}れる", a));
+        push(format!("{}せる", a));
+        push(format!("{}る", e));
+        push(format!("{}ば", e));
+        push(format!("{}う", o));
+        push(te.to_string());
+        push(ta.to_string());
+        push(format!("{}ら", ta));
+        push(format!("{}り", ta));
+    }
+    rules
+}
+
+// Every form the word could have come from, nearest first, without the word itself.
+pub fn deinflect(word: &str) -> Vec<String> {
+    let rules = rules();
+    let mut candidates: Vec<String> = Vec::new();
+    let mut current = vec![word.to_string()];
+    for _ in 0..MAX_STEPS {
+        let mut next = Vec::new();
+        for form in &current {
+            for (from, to) in rules {
+                if let Some(stem) = form.strip_suffix(from.as_str()) {
+                    // a lone ending isn't a word, する and くる are
+                    if stem.is_empty() && to.chars().count() < 2 {
+                        continue;
+                    }
+                    let candidate = format!("{}{}", stem, to);
+                    if candidate != word && !candidates.contains(&candidate) {
+                        candidates.push(candidate.clone());
+                        next.push(candidate);
+                    }
+                }
+            }
+        }
+        current = next;
+    }
+    candidates
+}
+
+// The dictionary form of the word if the dictionary has it or one of its
+// deinflections, the word itself first.
+pub fn dictionary_form(word: &str, is_word: impl Fn(&str) -> bool) -> Option<String> {
+    if is_word(word) {
+        return Some(word.to_string());
+    }
+    deinflect(word)
+        .into_iter()
+        .find(|candidate| is_word(candidate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(word: &str) -> bool {
+        [
+            "食べる",
+            "書く",
+            "高い",
+            "する",
+            "勉強",
+            "行く",
+            "読む",
+            "来る",
+        ]
+        .contains(&word)
+    }
+
+    #[test]
+    fn test_verbs() {
+        let form = |word| dictionary_form(word, dictionary);
+        assert_eq!(form("食べました").as_deref(), Some("食べる"));
+        assert_eq!(form("食べなかった").as_deref(), Some("食べる"));
+        assert_eq!(form("書かない").as_deref(), Some("書く"));
+        assert_eq!(form("書いて").as_deref(), Some("書く"));
+        assert_eq!(form("読んだ").as_deref(), Some("読む"));
+        assert_eq!(form("読める").as_deref(), Some("読む"));
+        assert_eq!(form("行って").as_deref(), Some("行く"));
+        assert_eq!(form("来ます").as_deref(), Some("来る"));
+        assert_eq!(form("しました").as_deref(), Some("する"));
+        assert_eq!(form("泳いだ"), None);
+    }
+
+    #[test]
+    fn test_chained() {
+        let form = |word| dictionary_form(word, dictionary);
+        assert_eq!(form("食べたくない").as_deref(), Some("食べる"));
+        assert_eq!(form("高くなかった").as_deref(), Some("高い"));
+        assert_eq!(form("書きたかった").as_deref(), Some("書く"));
+    }
+
+    #[test]
+    fn test_nothing_to_deinflect() {
+        assert!(deinflect("猫").is_empty());
+        assert_eq!(dictionary_form("勉強", dictionary).as_deref(), Some("勉強"));
+    }
+}
@@ -0,0 +1,300 @@
+// How hard an example sentence is to read, from 0 to 100, going by its length, the
+// number of words, how rare those words are in the corpus, and the grade and JLPT
+// level of its kanji.
+use crate::example_sentences::ExampleSentence;
+use crate::kanjidic::{kanji_in, Kanjidic};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// a sentence this long, or with this many words, counts as long
+const LONG_SENTENCE: f32 = 40.0;
+const MANY_WORDS: f32 = 12.0;
+// words used in this many sentences or more aren't rare at all
+const COMMON_WORD_SENTENCES: f32 = 1000.0;
+
+const LENGTH_WEIGHT: f32 = 0.2;
+const WORDS_WEIGHT: f32 = 0.15;
+const RARITY_WEIGHT: f32 = 0.3;
+const GRADE_WEIGHT: f32 = 0.2;
+const JLPT_WEIGHT: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Beginner,
+    Intermediate,
+    Advanced,
+}
+
+impl Level {
+    pub fn of(difficulty: u32) -> Level {
+        match difficulty {
+            0..=29 => Level::Beginner,
+            30..=54 => Level::Intermediate,
+            _ => Level::Advanced,
+        }
+    }
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Level::Beginner => "Beginner",
+            Level::Intermediate => "Intermediate",
+            Level::Advanced => "Advanced",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+// Which sentences the detail page shows, and in what order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SentenceOrder {
+    // the order of the corpus
+    #[default]
+    Corpus,
+    EasiestFirst,
+    HardestFirst,
+    Beginner,
+    Intermediate,
+    Advanced,
+}
+
+impl SentenceOrder {
+    pub const ALL: [SentenceOrder; 6] = [
+        SentenceOrder::Corpus,
+        SentenceOrder::EasiestFirst,
+        SentenceOrder::HardestFirst,
+        SentenceOrder::Beginner,
+        SentenceOrder::Intermediate,
+        SentenceOrder::Advanced,
+    ];
+}
+
+impl std::fmt::Display for SentenceOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            SentenceOrder::Corpus => "All sentences",
+            SentenceOrder::EasiestFirst => "Easiest first",
+            SentenceOrder::HardestFirst => "Hardest first",
+            SentenceOrder::Beginner => "Beginner only",
+            SentenceOrder::Intermediate => "Intermediate only",
+            SentenceOrder::Advanced => "Advanced only",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+// Which example sentence a new card gets, "sentence_policy" in config.json.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SentencePolicy {
+    // one where the word is the only unknown word, the shortest one if there is none
+    #[default]
+    IPlusOne,
+    Shortest,
+    Easiest,
+}
+
+// 0 for words that are everywhere, 1 for words only used once.
+fn rarity(sentence_count: usize) -> f32 {
+    let commonness = (1.0 + sentence_count as f32).ln() / (1.0 + COMMON_WORD_SENTENCES).ln();
+    (1.0 - commonness).clamp(0.0, 1.0)
+}
+
+// Kanji KANJIDIC2 doesn't know are as hard as they get.
+fn grade_difficulty(kanji: char, kanjidic: &Kanjidic) -> f32 {
+    match kanjidic.get(&kanji).and_then(|entry| entry.grade) {
+        Some(grade @ 1..=6) => (grade - 1) as f32 / 5.0 * 0.5,
+        Some(8) => 0.7,
+        _ => 1.0,
+    }
+}
+
+// The old JLPT went from 4 (easiest) to 1.
+fn jlpt_difficulty(kanji: char, kanjidic: &Kanjidic) -> f32 {
+    match kanjidic.get(&kanji).and_then(|entry| entry.jlpt) {
+        Some(level @ 1..=4) => (4 - level) as f32 / 3.0,
+        _ => 1.0,
+    }
+}
+
+fn mean(values: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f32
+    }
+}
+
+fn hardest(values: impl Iterator<Item = f32>) -> f32 {
+    values.fold(0.0, f32::max)
+}
+
+pub fn difficulty(
+    sentence: &ExampleSentence,
+    sentences_per_word: &HashMap<String, Vec<ExampleSentence>>,
+    kanjidic: &Kanjidic,
+) -> u32 {
+    let length = (sentence.japanese_text.chars().count() as f32 / LONG_SENTENCE).min(1.0);
+    let words = (sentence.indices.len() as f32 / MANY_WORDS).min(1.0);
+    let rarity = mean(sentence.indices.iter().map(|index_word| {
+        rarity(
+            sentences_per_word
+                .get(&index_word.headword)
+                .map(Vec::len)
+                .unwrap_or_default(),
+        )
+    }));
+    // one hard kanji is enough to trip over
+    let kanji = kanji_in(&sentence.japanese_text);
+    let grade = hardest(kanji.iter().map(|&kanji| grade_difficulty(kanji, kanjidic)));
+    let jlpt = hardest(kanji.iter().map(|&kanji| jlpt_difficulty(kanji, kanjidic)));
+
+    let score = LENGTH_WEIGHT * length
+        + WORDS_WEIGHT * words
+        + RARITY_WEIGHT * rarity
+        + GRADE_WEIGHT * grade
+        + JLPT_WEIGHT * jlpt;
+    (score * 100.0).round() as u32
+}
+
+pub fn easiest_sentence<'a>(
+    sentences: &'a [ExampleSentence],
+    sentences_per_word: &HashMap<String, Vec<ExampleSentence>>,
+    kanjidic: &Kanjidic,
+) -> Option<&'a ExampleSentence> {
+    sentences
+        .iter()
+        .min_by_key(|sentence| difficulty(sentence, sentences_per_word, kanjidic))
+}
+
+// Indices of the sentences to show, with their difficulty, in the requested order.
+pub fn order_sentences(
+    sentences: &[ExampleSentence],
+    order: SentenceOrder,
+    sentences_per_word: &HashMap<String, Vec<ExampleSentence>>,
+    kanjidic: &Kanjidic,
+) -> Vec<(usize, u32)> {
+    let mut scored: Vec<(usize, u32)> = sentences
+        .iter()
+        .enumerate()
+        .map(|(i, sentence)| (i, difficulty(sentence, sentences_per_word, kanjidic)))
+        .collect();
+    match order {
+        SentenceOrder::Corpus => {}
+        SentenceOrder::EasiestFirst => scored.sort_by_key(|&(_, difficulty)| difficulty),
+        SentenceOrder::HardestFirst => {
+            scored.sort_by_key(|&(_, difficulty)| std::cmp::Reverse(difficulty))
+        }
+        SentenceOrder::Beginner => scored.retain(|&(_, d)| Level::of(d) == Level::Beginner),
+        SentenceOrder::Intermediate => scored.retain(|&(_, d)| Level::of(d) == Level::Intermediate),
+        SentenceOrder::Advanced => scored.retain(|&(_, d)| Level::of(d) == Level::Advanced),
+    }
+    scored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::example_sentences::IndexWord;
+    use crate::kanjidic::KanjiEntry;
+
+    fn sentence(japanese: &str, headwords: &[&str]) -> ExampleSentence {
+        ExampleSentence {
+            japanese_text: japanese.to_string(),
+            indices: headwords
+                .iter()
+                .map(|headword| IndexWord {
+                    headword: headword.to_string(),
+                    reading: None,
+                    sense_number: None,
+                    form_in_sentence: None,
+                    good_and_checked: false,
+                })
+                .collect(),
+            ..ExampleSentence::default()
+        }
+    }
+
+    fn kanji(literal: char, grade: u32, jlpt: u32) -> (char, KanjiEntry) {
+        let entry = KanjiEntry {
+            literal,
+            on_readings: vec![],
+            kun_readings: vec![],
+            meanings: vec![],
+            stroke_count: None,
+            grade: Some(grade),
+            jlpt: Some(jlpt),
+            frequency: None,
+        };
+        (literal, entry)
+    }
+
+    fn corpus() -> (
+        Vec<ExampleSentence>,
+        HashMap<String, Vec<ExampleSentence>>,
+        Kanjidic,
+    ) {
+        let sentences = vec![
+            sentence(
+                "彼は忌憚のない意見を述べたが、誰にも顧みられなかった。",
+                &["彼", "忌憚", "意見", "述べる", "誰", "顧みる"],
+            ),
+            sentence("猫です。", &["猫", "です"]),
+            sentence("猫が好きです。", &["猫", "好き", "です"]),
+        ];
+        let mut sentences_per_word: HashMap<String, Vec<ExampleSentence>> = HashMap::new();
+        for sentence in &sentences {
+            for index_word in &sentence.indices {
+                let entry = sentences_per_word
+                    .entry(index_word.headword.clone())
+                    .or_default();
+                // the common words are in plenty of other sentences
+                let copies = if index_word.headword == "忌憚" {
+                    1
+                } else {
+                    500
+                };
+                entry.extend(vec![sentence.clone(); copies]);
+            }
+        }
+        let kanjidic = [kanji('猫', 8, 2), kanji('好', 4, 3), kanji('彼', 8, 2)]
+            .into_iter()
+            .collect();
+        (sentences, sentences_per_word, kanjidic)
+    }
+
+    #[test]
+    fn test_longer_and_rarer_is_harder() {
+        let (sentences, sentences_per_word, kanjidic) = corpus();
+        let scores: Vec<u32> = sentences
+            .iter()
+            .map(|sentence| difficulty(sentence, &sentences_per_word, &kanjidic))
+            .collect();
+        assert!(scores[1] < scores[2]);
+        assert!(scores[2] < scores[0]);
+        assert!(scores[0] <= 100);
+        assert_eq!(Level::of(scores[1]), Level::Beginner);
+        assert_eq!(Level::of(scores[0]), Level::Advanced);
+    }
+
+    #[test]
+    fn test_order_sentences() {
+        let (sentences, sentences_per_word, kanjidic) = corpus();
+        let order = |order| -> Vec<usize> {
+            order_sentences(&sentences, order, &sentences_per_word, &kanjidic)
+                .into_iter()
+                .map(|(i, _)| i)
+                .collect()
+        };
+        assert_eq!(order(SentenceOrder::Corpus), vec![0, 1, 2]);
+        assert_eq!(order(SentenceOrder::EasiestFirst), vec![1, 2, 0]);
+        assert_eq!(order(SentenceOrder::HardestFirst), vec![0, 2, 1]);
+        assert_eq!(order(SentenceOrder::Advanced), vec![0]);
+        assert_eq!(
+            easiest_sentence(&sentences, &sentences_per_word, &kanjidic),
+            Some(&sentences[1])
+        );
+    }
+}
@@ -146,6 +146,46 @@ pub fn wwwjdict_parser(input: &str) -> IResult<&str, ExampleSentence> {
     ))
 }
 
+// A piece of a sentence, linked to the index word it is (if any).
+#[derive(Debug, PartialEq, Eq)]
+pub struct SentencePart<'a> {
+    pub text: &'a str,
+    pub word: Option<&'a IndexWord>,
+}
+
+// Splits a sentence on its index words, in order. Index words that can't be found
+// in the text (after the previous one) are left out.
+pub fn split_on_index_words(sentence: &ExampleSentence) -> Vec<SentencePart<'_>> {
+    let text = sentence.japanese_text.as_str();
+    let mut parts: Vec<SentencePart> = Vec::new();
+    let mut cursor = 0;
+    for word in &sentence.indices {
+        let surface = word.form_in_sentence.as_ref().unwrap_or(&word.headword);
+        let position = match text[cursor..].find(surface.as_str()) {
+            Some(position) => cursor + position,
+            None => continue,
+        };
+        if position > cursor {
+            parts.push(SentencePart {
+                text: &text[cursor..position],
+                word: None,
+            });
+        }
+        parts.push(SentencePart {
+            text: &text[position..position + surface.len()],
+            word: Some(word),
+        });
+        cursor = position + surface.len();
+    }
+    if cursor < text.len() {
+        parts.push(SentencePart {
+            text: &text[cursor..],
+            word: None,
+        });
+    }
+    parts
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -175,6 +215,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_split_on_index_words() {
+        let (_, sentence) =
+            wwwjdict_parser("1	2	彼は愛してる。	He loves.	彼(かれ) は 愛する{愛してる} 猫\n")
+                .unwrap();
+        let parts: Vec<(&str, Option<&str>)> = split_on_index_words(&sentence)
+            .iter()
+            .map(|part| (part.text, part.word.map(|word| word.headword.as_str())))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("彼", Some("彼")),
+                ("は", Some("は")),
+                ("愛してる", Some("愛する")),
+                ("。", None),
+            ]
+        );
+    }
+
     #[test]
     fn test_scheme_complex_index() {
         let indexes = vec!(IndexWord {
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::prelude::*;
+
+pub const FAVORITES_FILE: &str = "favorites.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Favorite {
+    pub word: String,
+    pub reading: String,
+    pub translations: Vec<String>,
+    // unix timestamp in seconds
+    pub added: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FavoritesSort {
+    Newest,
+    Oldest,
+    Word,
+    Reading,
+}
+
+impl FavoritesSort {
+    pub const ALL: [FavoritesSort; 4] = [
+        FavoritesSort::Newest,
+        FavoritesSort::Oldest,
+        FavoritesSort::Word,
+        FavoritesSort::Reading,
+    ];
+}
+
+impl std::fmt::Display for FavoritesSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            FavoritesSort::Newest => "Newest first",
+            FavoritesSort::Oldest => "Oldest first",
+            FavoritesSort::Word => "Word",
+            FavoritesSort::Reading => "Reading",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+pub fn load_favorites() -> Vec<Favorite> {
+    std::fs::read_to_string(FAVORITES_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn store_favorites(favorites: &[Favorite]) -> Result<(), Box<dyn Error>> {
+    let mut file = std::fs::File::create(FAVORITES_FILE)?;
+    file.write_all(serde_json::to_string_pretty(favorites)?.as_bytes())?;
+    Ok(())
+}
+
+pub fn is_favorite(favorites: &[Favorite], word: &str, reading: &str) -> bool {
+    favorites
+        .iter()
+        .any(|favorite| favorite.word == word && favorite.reading == reading)
+}
+
+// Stars a word, or unstars it if it was starred already.
+pub fn toggle(favorites: &mut Vec<Favorite>, favorite: Favorite) {
+    if is_favorite(favorites, &favorite.word, &favorite.reading) {
+        favorites.retain(|f| !(f.word == favorite.word && f.reading == favorite.reading));
+    } else {
+        favorites.push(favorite);
+    }
+}
+
+// Indices of the favorites matching `filter`, in the requested order.
+pub fn filter_and_sort(favorites: &[Favorite], filter: &str, sort: FavoritesSort) -> Vec<usize> {
+    let filter = filter.to_lowercase();
+    let mut indices: Vec<usize> = (0..favorites.len())
+        .filter(|&i| {
+            let favorite = &favorites[i];
+            favorite.word.contains(&filter)
+                || favorite.reading.contains(&filter)
+                || favorite
+                    .translations
+                    .iter()
+                    .any(|translation| translation.to_lowercase().contains(&filter))
+        })
+        .collect();
+    match sort {
+        FavoritesSort::Newest => indices.sort_by_key(|&i| std::cmp::Reverse(favorites[i].added)),
+        FavoritesSort::Oldest => indices.sort_by_key(|&i| favorites[i].added),
+        FavoritesSort::Word => indices.sort_by(|&a, &b| favorites[a].word.cmp(&favorites[b].word)),
+        FavoritesSort::Reading => {
+            indices.sort_by(|&a, &b| favorites[a].reading.cmp(&favorites[b].reading))
+        }
+    }
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn favorite(word: &str, reading: &str, translation: &str, added: u64) -> Favorite {
+        Favorite {
+            word: word.to_string(),
+            reading: reading.to_string(),
+            translations: vec![translation.to_string()],
+            added,
+        }
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut favorites = Vec::new();
+        toggle(&mut favorites, favorite("猫", "ねこ", "cat", 1));
+        assert!(is_favorite(&favorites, "猫", "ねこ"));
+        toggle(&mut favorites, favorite("猫", "ねこ", "cat", 2));
+        assert!(favorites.is_empty());
+    }
+
+    #[test]
+    fn test_filter_and_sort() {
+        let favorites = vec![
+            favorite("猫", "ねこ", "cat", 1),
+            favorite("犬", "いぬ", "Dog", 3),
+            favorite("子犬", "こいぬ", "puppy", 2),
+        ];
+        assert_eq!(
+            filter_and_sort(&favorites, "", FavoritesSort::Newest),
+            vec![1, 2, 0]
+        );
+        assert_eq!(
+            filter_and_sort(&favorites, "", FavoritesSort::Reading),
+            vec![1, 2, 0]
+        );
+        assert_eq!(
+            filter_and_sort(&favorites, "dog", FavoritesSort::Oldest),
+            vec![1]
+        );
+        assert_eq!(
+            filter_and_sort(&favorites, "犬", FavoritesSort::Oldest),
+            vec![2, 1]
+        );
+    }
+}
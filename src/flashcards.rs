@@ -0,0 +1,197 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::error::Error;
+use std::io::prelude::*;
+
+pub const CSV_EXPORT_FILE: &str = "japanese_words_anki_import.txt";
+pub const APKG_EXPORT_FILE: &str = "japanese_words.apkg";
+pub const PENDING_CARDS_FILE: &str = "pending_flashcards.json";
+
+pub const ANKI_CONNECT_URL: &str = "http://localhost:8765";
+pub const ANKI_DECK_NAME: &str = "Japanese";
+pub const ANKI_NOTE_TYPE: &str = "Japanese Basic";
+
+// Field names of the "Japanese Basic" note type, in csv column order (see README).
+pub const ANKI_FIELD_NAMES: [&str; 7] = [
+    "Vocab",
+    "VocabKana",
+    "VocabTranslation",
+    "PartOfSpeech",
+    "Sentence",
+    "SentenceTranslation",
+    "Notes",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Flashcard {
+    pub vocab: String,
+    pub vocab_kana: String,
+    pub vocab_translation: String,
+    pub part_of_speech: String,
+    pub sentence: String,
+    pub sentence_translation: String,
+    #[serde(default)]
+    pub notes: String,
+}
+
+// The fields of a pending card that can be edited on the review screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashcardField {
+    VocabTranslation,
+    Sentence,
+    SentenceTranslation,
+    Notes,
+}
+
+impl Flashcard {
+    pub fn fields(&self) -> [&str; 7] {
+        [
+            &self.vocab,
+            &self.vocab_kana,
+            &self.vocab_translation,
+            &self.part_of_speech,
+            &self.sentence,
+            &self.sentence_translation,
+            &self.notes,
+        ]
+    }
+
+    pub fn set_field(&mut self, field: FlashcardField, value: String) {
+        match field {
+            FlashcardField::VocabTranslation => self.vocab_translation = value,
+            FlashcardField::Sentence => self.sentence = value,
+            FlashcardField::SentenceTranslation => self.sentence_translation = value,
+            FlashcardField::Notes => self.notes = value,
+        }
+    }
+}
+
+pub fn export_to_csv(cards: &[Flashcard]) -> Result<(), Box<dyn Error>> {
+    append_csv(CSV_EXPORT_FILE, cards)
+}
+
+pub fn append_csv(path: &str, cards: &[Flashcard]) -> Result<(), Box<dyn Error>> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(file);
+    for card in cards {
+        wtr.serialize(card)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// Body of an AnkiConnect "addNotes" request, see https://foosoft.net/projects/anki-connect/
+pub fn anki_connect_add_notes_request(cards: &[Flashcard]) -> serde_json::Value {
+    let notes: Vec<serde_json::Value> = cards
+        .iter()
+        .map(|card| {
+            let fields: serde_json::Map<String, serde_json::Value> = ANKI_FIELD_NAMES
+                .iter()
+                .zip(card.fields())
+                .map(|(name, value)| (name.to_string(), json!(value)))
+                .collect();
+            json!({
+                "deckName": ANKI_DECK_NAME,
+                "modelName": ANKI_NOTE_TYPE,
+                "fields": fields,
+                "tags": ["async-dict"],
+            })
+        })
+        .collect();
+    json!({
+        "action": "addNotes",
+        "version": 6,
+        "params": { "notes": notes },
+    })
+}
+
+// The cards of an "addNotes" request that Anki added, it answers with a null id for
+// each note it refused (e.g. a duplicate).
+pub fn anki_connect_added(cards: &[Flashcard], response: &serde_json::Value) -> Vec<Flashcard> {
+    let ids = response["result"].as_array().cloned().unwrap_or_default();
+    cards
+        .iter()
+        .zip(ids)
+        .filter(|(_, id)| !id.is_null())
+        .map(|(card, _)| card.clone())
+        .collect()
+}
+
+// Takes exported cards out of the pending ones, once each. Cards that were added or
+// edited while the export ran stay pending.
+pub fn remove_exported(pending: &mut Vec<Flashcard>, exported: &[Flashcard]) {
+    for card in exported {
+        if let Some(index) = pending.iter().position(|pending| pending == card) {
+            pending.remove(index);
+        }
+    }
+}
+
+// Pending cards survive restarts, so a review session can be finished later.
+pub fn load_pending_cards() -> Vec<Flashcard> {
+    std::fs::read_to_string(PENDING_CARDS_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn store_pending_cards(cards: &[Flashcard]) -> Result<(), Box<dyn Error>> {
+    let mut file = std::fs::File::create(PENDING_CARDS_FILE)?;
+    file.write_all(serde_json::to_string_pretty(cards)?.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anki_connect_request_maps_fields_in_order() {
+        let card = Flashcard {
+            vocab: "猫".to_string(),
+            vocab_kana: "ねこ".to_string(),
+            vocab_translation: "cat".to_string(),
+            part_of_speech: "noun".to_string(),
+            sentence: "猫が好き。".to_string(),
+            sentence_translation: "I like cats.".to_string(),
+            notes: "".to_string(),
+        };
+        let request = anki_connect_add_notes_request(&[card]);
+        let note = &request["params"]["notes"][0];
+        assert_eq!(request["action"], "addNotes");
+        assert_eq!(note["modelName"], ANKI_NOTE_TYPE);
+        assert_eq!(note["fields"]["Vocab"], "猫");
+        assert_eq!(note["fields"]["SentenceTranslation"], "I like cats.");
+    }
+
+    #[test]
+    fn test_remove_exported() {
+        let card = |vocab: &str| Flashcard {
+            vocab: vocab.to_string(),
+            ..Flashcard::default()
+        };
+        let sent = vec![card("猫"), card("犬"), card("猫")];
+        let response = json!({ "result": [1, null, 3], "error": null });
+        let added = anki_connect_added(&sent, &response);
+        assert_eq!(added, vec![card("猫"), card("猫")]);
+
+        // 鳥 was added while the cards were being sent
+        let mut pending = vec![card("猫"), card("犬"), card("猫"), card("鳥")];
+        remove_exported(&mut pending, &added);
+        assert_eq!(pending, vec![card("犬"), card("鳥")]);
+    }
+
+    #[test]
+    fn test_set_field() {
+        let mut card = Flashcard::default();
+        card.set_field(FlashcardField::Notes, "transitive".to_string());
+        card.set_field(FlashcardField::Sentence, "猫だ。".to_string());
+        assert_eq!(card.notes, "transitive");
+        assert_eq!(card.sentence, "猫だ。");
+    }
+}
@@ -0,0 +1,237 @@
+use crate::example_sentences::{split_on_index_words, ExampleSentence, IndexWord};
+use std::collections::HashMap;
+
+// A piece of a sentence, with the reading to show above it if it contains kanji.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuriganaSegment {
+    pub text: String,
+    pub reading: Option<String>,
+}
+
+impl FuriganaSegment {
+    fn plain(text: &str) -> Self {
+        FuriganaSegment {
+            text: text.to_string(),
+            reading: None,
+        }
+    }
+}
+
+pub fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{4e00}'..='\u{9fff}' | '\u{3400}'..='\u{4dbf}' | '々' | '〆' | 'ヶ')
+}
+
+pub fn katakana_to_hiragana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{30a1}'..='\u{30f6}' if c != 'ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+// Splits a word into alternating runs of kanji and non-kanji characters.
+fn runs(word: &str) -> Vec<(String, bool)> {
+    let mut runs: Vec<(String, bool)> = Vec::new();
+    for c in word.chars() {
+        match runs.last_mut() {
+            Some((run, kanji)) if *kanji == is_kanji(c) => run.push(c),
+            _ => runs.push((c.to_string(), is_kanji(c))),
+        }
+    }
+    runs
+}
+
+// Finds the reading of every run in a word, e.g. 思い出す + おもいだす gives
+// [おも, い, だ, す]. Kana runs must match the reading literally, kanji runs take
+// whatever is left in between.
+fn align(runs: &[(String, bool)], reading: &[char]) -> Option<Vec<String>> {
+    let ((run, kanji), rest) = match runs.split_first() {
+        Some(first_and_rest) => first_and_rest,
+        None if reading.is_empty() => return Some(Vec::new()),
+        None => return None,
+    };
+    if *kanji {
+        (1..=reading.len()).find_map(|len| {
+            let mut aligned = align(rest, &reading[len..])?;
+            aligned.insert(0, reading[..len].iter().collect());
+            Some(aligned)
+        })
+    } else {
+        let run_chars: Vec<char> = katakana_to_hiragana(run).chars().collect();
+        if !reading.starts_with(&run_chars) {
+            return None;
+        }
+        let mut aligned = align(rest, &reading[run_chars.len()..])?;
+        aligned.insert(0, run.to_string());
+        Some(aligned)
+    }
+}
+
+// Readings for the kanji runs of a headword, used to annotate inflected forms of it.
+fn kanji_readings(headword: &str, reading: &str) -> HashMap<String, String> {
+    let headword_runs = runs(headword);
+    let reading: Vec<char> = katakana_to_hiragana(reading).chars().collect();
+    match align(&headword_runs, &reading) {
+        Some(aligned) => headword_runs
+            .into_iter()
+            .zip(aligned)
+            .filter(|((_, kanji), _)| *kanji)
+            .map(|((run, _), reading)| (run, reading))
+            .collect(),
+        None => HashMap::new(),
+    }
+}
+
+fn word_segments(surface: &str, headword: &str, reading: Option<&String>) -> Vec<FuriganaSegment> {
+    let readings = match reading {
+        Some(reading) => kanji_readings(headword, reading),
+        None => HashMap::new(),
+    };
+    runs(surface)
+        .into_iter()
+        .map(|(run, kanji)| FuriganaSegment {
+            reading: if kanji {
+                readings.get(&run).cloned()
+            } else {
+                None
+            },
+            text: run,
+        })
+        .collect()
+}
+
+// Annotates a sentence using the readings of its index words. Index words without
+// a reading fall back to `dictionary_readings` (headword -> reading).
+pub fn sentence_furigana(
+    sentence: &ExampleSentence,
+    dictionary_readings: &HashMap<String, String>,
+) -> Vec<FuriganaSegment> {
+    let segments = split_on_index_words(sentence)
+        .into_iter()
+        .flat_map(|part| match part.word {
+            Some(word) => index_word_furigana(part.text, word, dictionary_readings),
+            None => vec![FuriganaSegment::plain(part.text)],
+        })
+        .collect();
+    merge_plain_segments(segments)
+}
+
+// Annotates one index word as it's written in the sentence.
+pub fn index_word_furigana(
+    surface: &str,
+    word: &IndexWord,
+    dictionary_readings: &HashMap<String, String>,
+) -> Vec<FuriganaSegment> {
+    let reading = word
+        .reading
+        .as_ref()
+        .or_else(|| dictionary_readings.get(&word.headword));
+    word_segments(surface, &word.headword, reading)
+}
+
+fn merge_plain_segments(segments: Vec<FuriganaSegment>) -> Vec<FuriganaSegment> {
+    let mut merged: Vec<FuriganaSegment> = Vec::new();
+    for segment in segments {
+        match merged.last_mut() {
+            Some(last) if last.reading.is_none() && segment.reading.is_none() => {
+                last.text.push_str(&segment.text)
+            }
+            _ => merged.push(segment),
+        }
+    }
+    merged
+}
+
+// Anki's furigana syntax: 漢字[かんじ], a space marks where the annotated text starts.
+// https://docs.ankiweb.net/templates/fields.html#ruby-characters
+pub fn to_anki_furigana(segments: &[FuriganaSegment]) -> String {
+    let mut anki = String::new();
+    for segment in segments {
+        match &segment.reading {
+            Some(reading) => {
+                if !anki.is_empty() && !anki.ends_with(' ') {
+                    anki.push(' ');
+                }
+                anki.push_str(&format!("{}[{}]", segment.text, reading));
+            }
+            None => anki.push_str(&segment.text),
+        }
+    }
+    anki
+}
+
+// Undoes `to_anki_furigana`, giving back the plain sentence.
+pub fn strip_anki_furigana(anki: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = anki.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            ' ' if matches!(chars.peek(), Some(next) if is_kanji(*next)) => {}
+            _ => plain.push(c),
+        }
+    }
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::example_sentences::wwwjdict_parser;
+
+    fn segment(text: &str, reading: Option<&str>) -> FuriganaSegment {
+        FuriganaSegment {
+            text: text.to_string(),
+            reading: reading.map(|r| r.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_okurigana_alignment() {
+        let readings = kanji_readings("思い出す", "おもいだす");
+        assert_eq!(readings.get("思"), Some(&"おも".to_string()));
+        assert_eq!(readings.get("出"), Some(&"だ".to_string()));
+        assert_eq!(
+            word_segments("思いだした", "思い出す", Some(&"おもいだす".to_string())),
+            vec![segment("思", Some("おも")), segment("いだした", None)]
+        );
+    }
+
+    #[test]
+    fn test_sentence_furigana_with_dictionary_fallback() {
+        let (_, sentence) = wwwjdict_parser(
+            "75198	328521	総員、脱出せよ！	All hands, abandon ship!	総員~ 脱出 為る(する){せよ}\n",
+        )
+        .unwrap();
+        let dictionary_readings = HashMap::from([("脱出".to_string(), "だっしゅつ".to_string())]);
+        assert_eq!(
+            sentence_furigana(&sentence, &dictionary_readings),
+            vec![
+                segment("総員、", None),
+                segment("脱出", Some("だっしゅつ")),
+                segment("せよ！", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_anki_furigana_round_trip() {
+        let (_, sentence) = wwwjdict_parser("127240	276849	男の子は結局男の子であることを思いだした。	I remembered that boys will be boys.	男の子(おとこのこ) は|1 結局 男の子(おとこのこ) である 事(こと){こと} を 思い出す{思いだした}\n").unwrap();
+        let dictionary_readings =
+            HashMap::from([("思い出す".to_string(), "おもいだす".to_string())]);
+        let segments = sentence_furigana(&sentence, &dictionary_readings);
+        let anki = to_anki_furigana(&segments);
+        assert_eq!(
+            anki,
+            "男[おとこ]の 子[こ]は結局 男[おとこ]の 子[こ]であることを 思[おも]いだした。"
+        );
+        assert_eq!(strip_anki_furigana(&anki), sentence.japanese_text);
+    }
+}
@@ -0,0 +1,223 @@
+// Grammar points, found in the example sentences with regular expressions.
+// Patterns can be added (or the built-in ones changed) in grammar_patterns.json, e.g.
+// [{ "name": "〜ないで", "pattern": "ないで", "jlpt": 4, "explanation": "without doing" }]
+use crate::example_sentences::ExampleSentence;
+use crate::flashcards::Flashcard;
+use crate::sentence_index::SentenceMatch;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+pub const GRAMMAR_FILE: &str = "grammar_patterns.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrammarPattern {
+    pub name: String,
+    // a regular expression, matched against the Japanese text
+    pub pattern: String,
+    // the N level, 5 is the easiest
+    #[serde(default)]
+    pub jlpt: Option<u8>,
+    #[serde(default)]
+    pub explanation: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompiledPattern {
+    pub grammar: GrammarPattern,
+    pub regex: Regex,
+}
+
+// name, pattern, JLPT level, explanation
+#[rustfmt::skip]
+const DEFAULT_PATTERNS: [(&str, &str, u8, &str); 20] = [
+    ("〜ながら", r"[\p{Han}\p{Hiragana}]ながら", 4, "while doing; two actions at the same time (verb stem + ながら)"),
+    ("〜たことがある", r"[ただ]ことが(ある|あります|あった|ない|ありません)", 4, "have done before; experience (verb た-form + ことがある)"),
+    ("〜てもいい", r"[てで]も(いい|良い|よい|かまわない|構わない|いいです)", 4, "it's fine to; may (て-form + もいい)"),
+    ("〜なければならない", r"なければ(ならない|なりません|ならなかった|いけない|いけません)", 4, "must; have to (ない-form without い + ければならない)"),
+    ("〜ようにする", r"ように(する|して|した|しな|します|しよう)", 4, "to make an effort to; to make sure that"),
+    ("〜ようになる", r"ように(なる|なった|なり|なって|なります)", 4, "to come to; to reach the point where"),
+    ("〜ことにする", r"ことに(する|した|しよう|します|しました|して)", 4, "to decide to (dictionary form + ことにする)"),
+    ("〜つもりだ", r"つもり(だ|です|で|は|だった|でした)", 4, "to intend to; to plan to"),
+    ("〜すぎる", r"(すぎ|過ぎ)(る|た|て|ます|ない)", 4, "too much; excessively (stem + すぎる)"),
+    ("〜てしまう", r"[てで]しま(う|った|います|いました)|[ちじ]ゃ(う|った)", 4, "to end up doing; to do completely (often with regret)"),
+    ("〜かもしれない", r"かも(しれ|知れ)(ない|ません|なかった)", 4, "might; maybe"),
+    ("〜ために", r"ために", 4, "in order to; for the sake of; because of"),
+    ("〜ところだ", r"ところ(だ|です|だった|でした)", 4, "about to / in the middle of / just did (depending on the verb form)"),
+    ("〜はずだ", r"はず(だ|です|がない|はない|だった)", 3, "should be; is expected to"),
+    ("〜わけではない", r"わけ(では|じゃ)(ない|なかった|ありません)", 3, "it doesn't mean that; it's not that"),
+    ("〜ば〜ほど", r"ば.{1,6}ほど", 3, "the more ... the more"),
+    ("〜によって", r"によって|により|による", 3, "by means of; depending on; due to"),
+    ("〜ばかりか", r"ばかりか", 2, "not only ... but also"),
+    ("〜にもかかわらず", r"にも(かかわらず|関わらず|拘らず)", 2, "in spite of; despite"),
+    ("〜わけにはいかない", r"わけには(いかない|いきません|いかなかった)", 2, "cannot afford to; can't very well"),
+];
+
+pub fn default_patterns() -> Vec<GrammarPattern> {
+    DEFAULT_PATTERNS
+        .iter()
+        .map(|(name, pattern, jlpt, explanation)| GrammarPattern {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            jlpt: Some(*jlpt),
+            explanation: explanation.to_string(),
+        })
+        .collect()
+}
+
+// The user's patterns replace the built-in ones with the same name, the others are added.
+pub fn merge_patterns(
+    mut patterns: Vec<GrammarPattern>,
+    user_patterns: Vec<GrammarPattern>,
+) -> Vec<GrammarPattern> {
+    for user_pattern in user_patterns {
+        match patterns.iter_mut().find(|p| p.name == user_pattern.name) {
+            Some(pattern) => *pattern = user_pattern,
+            None => patterns.push(user_pattern),
+        }
+    }
+    patterns
+}
+
+// Easiest first, patterns without a level last.
+pub fn compile_patterns(patterns: Vec<GrammarPattern>) -> Vec<CompiledPattern> {
+    let mut compiled: Vec<CompiledPattern> = patterns
+        .into_iter()
+        .filter_map(|grammar| match Regex::new(&grammar.pattern) {
+            Ok(regex) => Some(CompiledPattern { grammar, regex }),
+            Err(error) => {
+                eprintln!("grammar: skipping {}: {}", grammar.name, error);
+                None
+            }
+        })
+        .collect();
+    compiled.sort_by_key(|pattern| std::cmp::Reverse(pattern.grammar.jlpt.unwrap_or_default()));
+    compiled
+}
+
+pub fn load_patterns() -> Vec<CompiledPattern> {
+    let user_patterns: Vec<GrammarPattern> = match std::fs::read_to_string(GRAMMAR_FILE) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|error| {
+            eprintln!("grammar: could not parse {}: {}", GRAMMAR_FILE, error);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    };
+    compile_patterns(merge_patterns(default_patterns(), user_patterns))
+}
+
+// The sentences the pattern matches, shortest first.
+pub fn matching_sentences(
+    regex: &Regex,
+    sentences: &[ExampleSentence],
+    limit: usize,
+) -> Vec<SentenceMatch> {
+    let mut matches: Vec<SentenceMatch> = sentences
+        .iter()
+        .enumerate()
+        .filter_map(|(id, sentence)| {
+            let highlights: Vec<std::ops::Range<usize>> = regex
+                .find_iter(&sentence.japanese_text)
+                .map(|found| found.range())
+                .collect();
+            if highlights.is_empty() {
+                return None;
+            }
+            Some(SentenceMatch {
+                id,
+                score: 1.0 / sentence.japanese_text.chars().count() as f32,
+                highlights,
+                english: false,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+    matches.truncate(limit);
+    matches
+}
+
+pub fn grammar_flashcard(grammar: &GrammarPattern, sentence: &ExampleSentence) -> Flashcard {
+    Flashcard {
+        vocab: grammar.name.clone(),
+        vocab_kana: "".to_string(),
+        vocab_translation: grammar.explanation.clone(),
+        part_of_speech: "grammar".to_string(),
+        sentence: sentence.japanese_text.clone(),
+        sentence_translation: sentence.english_text.clone(),
+        notes: match grammar.jlpt {
+            Some(level) => format!("JLPT N{}", level),
+            None => "".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentence(japanese: &str) -> ExampleSentence {
+        ExampleSentence {
+            japanese_text: japanese.to_string(),
+            ..ExampleSentence::default()
+        }
+    }
+
+    #[test]
+    fn test_default_patterns_compile() {
+        assert_eq!(
+            compile_patterns(default_patterns()).len(),
+            DEFAULT_PATTERNS.len()
+        );
+    }
+
+    #[test]
+    fn test_merge_patterns() {
+        let user_patterns = vec![
+            GrammarPattern {
+                name: "〜ながら".to_string(),
+                pattern: "ながら".to_string(),
+                jlpt: None,
+                explanation: "while".to_string(),
+            },
+            GrammarPattern {
+                name: "〜ないで".to_string(),
+                pattern: "ないで".to_string(),
+                jlpt: Some(4),
+                explanation: "without doing".to_string(),
+            },
+        ];
+        let patterns = merge_patterns(default_patterns(), user_patterns);
+        assert_eq!(patterns.len(), DEFAULT_PATTERNS.len() + 1);
+        assert_eq!(patterns[0].explanation, "while");
+        assert_eq!(patterns.last().unwrap().name, "〜ないで");
+    }
+
+    #[test]
+    fn test_bad_patterns_are_skipped() {
+        let patterns = vec![GrammarPattern {
+            name: "broken".to_string(),
+            pattern: "(".to_string(),
+            jlpt: None,
+            explanation: "".to_string(),
+        }];
+        assert!(compile_patterns(patterns).is_empty());
+    }
+
+    #[test]
+    fn test_matching_sentences() {
+        let sentences = vec![
+            sentence("音楽を聞きながら勉強します。"),
+            sentence("猫が好きです。"),
+            sentence("歩きながら話した。"),
+        ];
+        let regex = Regex::new(DEFAULT_PATTERNS[0].1).unwrap();
+        let matches = matching_sentences(&regex, &sentences, 10);
+        assert_eq!(
+            matches.iter().map(|found| found.id).collect::<Vec<_>>(),
+            vec![2, 0]
+        );
+        assert_eq!(
+            &sentences[2].japanese_text[matches[0].highlights[0].clone()],
+            "きながら"
+        );
+    }
+}
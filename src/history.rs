@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::prelude::*;
+
+pub const SEARCH_HISTORY_FILE: &str = "search_history.json";
+const MAX_HISTORY_ENTRIES: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub query: String,
+    // unix timestamp in seconds
+    pub timestamp: u64,
+    pub result_count: usize,
+}
+
+pub fn load_history() -> Vec<HistoryEntry> {
+    std::fs::read_to_string(SEARCH_HISTORY_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn store_history(history: &[HistoryEntry]) -> Result<(), Box<dyn Error>> {
+    let mut file = std::fs::File::create(SEARCH_HISTORY_FILE)?;
+    file.write_all(serde_json::to_string_pretty(history)?.as_bytes())?;
+    Ok(())
+}
+
+// Most recent search first, searching for something again moves it to the top.
+pub fn record(history: &mut Vec<HistoryEntry>, query: &str, result_count: usize, now: u64) {
+    history.retain(|entry| entry.query != query);
+    history.insert(
+        0,
+        HistoryEntry {
+            query: query.to_string(),
+            timestamp: now,
+            result_count,
+        },
+    );
+    history.truncate(MAX_HISTORY_ENTRIES);
+}
+
+pub fn time_ago(timestamp: u64, now: u64) -> String {
+    let seconds = now.saturating_sub(timestamp);
+    let (amount, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    format!(
+        "{} {}{} ago",
+        amount,
+        unit,
+        if amount == 1 { "" } else { "s" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_moves_repeated_query_to_top() {
+        let mut history = Vec::new();
+        record(&mut history, "猫", 3, 10);
+        record(&mut history, "dog", 20, 20);
+        record(&mut history, "猫", 4, 30);
+        assert_eq!(
+            history,
+            vec![
+                HistoryEntry {
+                    query: "猫".to_string(),
+                    timestamp: 30,
+                    result_count: 4
+                },
+                HistoryEntry {
+                    query: "dog".to_string(),
+                    timestamp: 20,
+                    result_count: 20
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_time_ago() {
+        assert_eq!(time_ago(100, 110), "just now");
+        assert_eq!(time_ago(0, 60), "1 minute ago");
+        assert_eq!(time_ago(0, 7200), "2 hours ago");
+        assert_eq!(time_ago(0, 3 * 86400), "3 days ago");
+    }
+}
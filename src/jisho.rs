@@ -2,6 +2,7 @@ use serde::Deserialize;
 
 #[derive(Deserialize, Default, Clone, Debug)]
 pub struct JishoResponse {
+    #[allow(dead_code)]
     pub meta: Status,
     pub data: Vec<Entry>,
 }
@@ -12,9 +13,12 @@ pub struct Status {
 #[derive(Deserialize, Default, Clone, Debug)]
 pub struct Entry {
     pub slug: String,
-    // is_common: Option<bool>,
+    #[serde(default)]
+    pub is_common: Option<bool>,
     // tags: Vec<String>,
-    // jlpt: Vec<String>,
+    // e.g. ["jlpt-n5"]
+    #[serde(default)]
+    pub jlpt: Vec<String>,
     pub japanese: Vec<JapaneseWord>,
     pub senses: Vec<Sense>,
     // attribution: Attribution,
@@ -24,6 +28,7 @@ pub struct JapaneseWord {
     // word: Option<String>,
     pub reading: Option<String>,
 }
+#[allow(dead_code)]
 #[derive(Deserialize, Default, Clone, Debug)]
 pub struct Attribution {
     // jmdict: serde_json::Value,
@@ -0,0 +1,165 @@
+// Kanji information from KANJIDIC2:
+// http://www.edrdg.org/wiki/index.php/KANJIDIC_Project
+use crate::furigana::is_kanji;
+use std::collections::HashMap;
+
+pub const KANJIDIC_FILE: &str = "resources/kanjidic2.xml";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KanjiEntry {
+    pub literal: char,
+    pub on_readings: Vec<String>,
+    pub kun_readings: Vec<String>,
+    pub meanings: Vec<String>,
+    pub stroke_count: Option<u32>,
+    // 1-6 are taught in elementary school, 8 in junior high, 9-10 are jinmeiyou
+    pub grade: Option<u32>,
+    // the old 4 level JLPT, 1 is the hardest
+    pub jlpt: Option<u32>,
+    // rank among the 2500 most used kanji in newspapers
+    pub frequency: Option<u32>,
+}
+
+impl KanjiEntry {
+    // e.g. "11 strokes, grade 8, JLPT 2, #1702 most used"
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(stroke_count) = self.stroke_count {
+            parts.push(format!("{} strokes", stroke_count));
+        }
+        if let Some(grade) = self.grade {
+            parts.push(format!("grade {}", grade));
+        }
+        if let Some(jlpt) = self.jlpt {
+            parts.push(format!("JLPT {}", jlpt));
+        }
+        if let Some(frequency) = self.frequency {
+            parts.push(format!("#{} most used", frequency));
+        }
+        parts.join(", ")
+    }
+}
+
+pub type Kanjidic = HashMap<char, KanjiEntry>;
+
+pub fn parse_kanjidic(xml: &str) -> Result<Kanjidic, roxmltree::Error> {
+    // the file starts with its DTD, which roxmltree refuses by default
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..roxmltree::ParsingOptions::default()
+    };
+    let document = roxmltree::Document::parse_with_options(xml, options)?;
+    let kanjidic = document
+        .root_element()
+        .children()
+        .filter(|node| node.has_tag_name("character"))
+        .filter_map(|character| {
+            let literal = child_text(character, "literal")?.chars().next()?;
+            let mut entry = KanjiEntry {
+                literal,
+                ..KanjiEntry::default()
+            };
+            for node in character.descendants() {
+                let text = || node.text().unwrap_or_default().to_string();
+                let number = || node.text().and_then(|text| text.parse().ok());
+                match node.tag_name().name() {
+                    "reading" => match node.attribute("r_type") {
+                        Some("ja_on") => entry.on_readings.push(text()),
+                        Some("ja_kun") => entry.kun_readings.push(text()),
+                        _ => {}
+                    },
+                    // meanings without a language are the English ones
+                    "meaning" if node.attribute("m_lang").is_none() => entry.meanings.push(text()),
+                    // the first stroke count is the right one, others are common miscounts
+                    "stroke_count" if entry.stroke_count.is_none() => entry.stroke_count = number(),
+                    "grade" => entry.grade = number(),
+                    "jlpt" => entry.jlpt = number(),
+                    "freq" => entry.frequency = number(),
+                    _ => {}
+                }
+            }
+            Some((literal, entry))
+        })
+        .collect();
+    Ok(kanjidic)
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.has_tag_name(name))
+        .and_then(|child| child.text())
+}
+
+// The kanji of a word in order, without repeats or iteration marks.
+pub fn kanji_in(word: &str) -> Vec<char> {
+    let mut kanji: Vec<char> = Vec::new();
+    for c in word
+        .chars()
+        .filter(|c| is_kanji(*c) && !matches!(c, '々' | '〆' | 'ヶ'))
+    {
+        if !kanji.contains(&c) {
+            kanji.push(c);
+        }
+    }
+    kanji
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KANJIDIC_SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE kanjidic2 [
+<!ELEMENT kanjidic2 (header,character*)>
+]>
+<kanjidic2>
+<header><file_version>4</file_version></header>
+<character>
+<literal>猫</literal>
+<misc>
+<grade>8</grade>
+<stroke_count>11</stroke_count>
+<stroke_count>12</stroke_count>
+<freq>1702</freq>
+<jlpt>2</jlpt>
+</misc>
+<reading_meaning>
+<rmgroup>
+<reading r_type="pinyin">mao1</reading>
+<reading r_type="ja_on">ビョウ</reading>
+<reading r_type="ja_kun">ねこ</reading>
+<meaning>cat</meaning>
+<meaning m_lang="fr">chat</meaning>
+</rmgroup>
+</reading_meaning>
+</character>
+</kanjidic2>"#;
+
+    #[test]
+    fn test_parse_kanjidic() {
+        let kanjidic = parse_kanjidic(KANJIDIC_SAMPLE).unwrap();
+        assert_eq!(
+            kanjidic.get(&'猫'),
+            Some(&KanjiEntry {
+                literal: '猫',
+                on_readings: vec!["ビョウ".to_string()],
+                kun_readings: vec!["ねこ".to_string()],
+                meanings: vec!["cat".to_string()],
+                stroke_count: Some(11),
+                grade: Some(8),
+                jlpt: Some(2),
+                frequency: Some(1702),
+            })
+        );
+        assert_eq!(
+            kanjidic[&'猫'].summary(),
+            "11 strokes, grade 8, JLPT 2, #1702 most used"
+        );
+    }
+
+    #[test]
+    fn test_kanji_in() {
+        assert_eq!(kanji_in("思い出す"), vec!['思', '出']);
+        assert_eq!(kanji_in("人々と人"), vec!['人']);
+    }
+}
@@ -0,0 +1,215 @@
+// Stroke order diagrams from KanjiVG: https://kanjivg.tagaini.net
+// Every kanji has its own svg file named after its code point, e.g. 猫 is "0732b.svg".
+use crate::apkg::MediaFile;
+use crate::flashcards::Flashcard;
+use crate::kanjidic::kanji_in;
+use std::path::PathBuf;
+
+pub const KANJIVG_DIR: &str = "resources/kanjivg";
+
+const DRAWN_COLOR: &str = "#000000";
+const CURRENT_COLOR: &str = "#d9534f";
+const UPCOMING_COLOR: &str = "#dddddd";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrokeOrder {
+    pub kanji: char,
+    // svg path data of each stroke, in writing order
+    strokes: Vec<String>,
+    // where the stroke numbers go
+    numbers: Vec<(f32, f32)>,
+}
+
+impl StrokeOrder {
+    pub fn stroke_count(&self) -> usize {
+        self.strokes.len()
+    }
+
+    // The first `drawn` strokes with their numbers, the rest greyed out. With
+    // `highlight` the last drawn stroke stands out as the one being written.
+    pub fn svg(&self, drawn: usize, highlight: bool) -> String {
+        let mut svg = String::from(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="109" height="109" viewBox="0 0 109 109">"#,
+        );
+        svg.push_str(
+            r#"<g style="fill:none;stroke-width:3;stroke-linecap:round;stroke-linejoin:round;">"#,
+        );
+        for (n, stroke) in self.strokes.iter().enumerate() {
+            let color = if n + 1 == drawn && highlight {
+                CURRENT_COLOR
+            } else if n < drawn {
+                DRAWN_COLOR
+            } else {
+                UPCOMING_COLOR
+            };
+            svg.push_str(&format!(r#"<path d="{}" stroke="{}"/>"#, stroke, color));
+        }
+        svg.push_str(r#"</g><g style="font-size:8;fill:#808080">"#);
+        for (n, (x, y)) in self.numbers.iter().enumerate().take(drawn) {
+            svg.push_str(&format!(r#"<text x="{}" y="{}">{}</text>"#, x, y, n + 1));
+        }
+        svg.push_str("</g></svg>");
+        svg
+    }
+}
+
+pub fn parse_kanjivg(kanji: char, svg: &str) -> Result<StrokeOrder, roxmltree::Error> {
+    // The kvg: attribute prefix is only declared in the DTD, which roxmltree
+    // doesn't apply, so declare it on the root element instead.
+    let svg = if svg.contains("xmlns:kvg=") {
+        svg.to_string()
+    } else {
+        svg.replacen(
+            "<svg ",
+            r#"<svg xmlns:kvg="http://kanjivg.tagaini.net" "#,
+            1,
+        )
+    };
+    let options = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..roxmltree::ParsingOptions::default()
+    };
+    let document = roxmltree::Document::parse_with_options(&svg, options)?;
+
+    let strokes = document
+        .descendants()
+        .filter(|node| node.has_tag_name("path"))
+        .filter_map(|node| node.attribute("d"))
+        .map(str::to_string)
+        .collect();
+    // the numbers are placed with transform="matrix(1 0 0 1 x y)"
+    let numbers = document
+        .descendants()
+        .filter(|node| node.has_tag_name("text"))
+        .filter_map(|node| {
+            let matrix: Vec<f32> = node
+                .attribute("transform")?
+                .trim_start_matches("matrix(")
+                .trim_end_matches(')')
+                .split_whitespace()
+                .filter_map(|number| number.parse().ok())
+                .collect();
+            match matrix[..] {
+                [_, _, _, _, x, y] => Some((x, y)),
+                _ => None,
+            }
+        })
+        .collect();
+    Ok(StrokeOrder {
+        kanji,
+        strokes,
+        numbers,
+    })
+}
+
+pub fn kanjivg_file(kanji: char) -> PathBuf {
+    PathBuf::from(KANJIVG_DIR).join(format!("{:05x}.svg", kanji as u32))
+}
+
+// Diagrams for the kanji of a word, skipping those KanjiVG doesn't have.
+pub fn load_stroke_orders(word: &str) -> Vec<StrokeOrder> {
+    kanji_in(word)
+        .into_iter()
+        .filter_map(|kanji| {
+            let svg = std::fs::read_to_string(kanjivg_file(kanji)).ok()?;
+            parse_kanjivg(kanji, &svg).ok()
+        })
+        .collect()
+}
+
+pub fn media_file_name(kanji: char) -> String {
+    format!("kanjivg_{:05x}.svg", kanji as u32)
+}
+
+// Adds the stroke order diagrams of each card's word to its notes, and returns
+// the images that have to go into the .apkg with them.
+pub fn with_stroke_order_media(cards: &[Flashcard]) -> (Vec<Flashcard>, Vec<MediaFile>) {
+    let mut media: Vec<MediaFile> = Vec::new();
+    let cards = cards
+        .iter()
+        .map(|card| {
+            let mut card = card.clone();
+            let stroke_orders = load_stroke_orders(&card.vocab);
+            if stroke_orders.is_empty() {
+                return card;
+            }
+            let mut images = String::new();
+            for stroke_order in stroke_orders {
+                let name = media_file_name(stroke_order.kanji);
+                images.push_str(&format!(r#"<img src="{}">"#, name));
+                if !media.iter().any(|file| file.name == name) {
+                    let data = stroke_order
+                        .svg(stroke_order.stroke_count(), false)
+                        .into_bytes();
+                    media.push(MediaFile { name, data });
+                }
+            }
+            card.notes = if card.notes.is_empty() {
+                images
+            } else {
+                format!("{}<br>{}", card.notes, images)
+            };
+            card
+        })
+        .collect();
+    (cards, media)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 人 from KanjiVG, with the DTD that declares the kvg: attributes
+    const KANJIVG_SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.0//EN" "http://www.w3.org/TR/2001/REC-SVG-20010904/DTD/svg10.dtd" [
+<!ATTLIST g
+xmlns:kvg CDATA #FIXED "http://kanjivg.tagaini.net"
+kvg:element CDATA #IMPLIED >
+<!ATTLIST path
+xmlns:kvg CDATA #FIXED "http://kanjivg.tagaini.net"
+kvg:type CDATA #IMPLIED >
+]>
+<svg xmlns="http://www.w3.org/2000/svg" width="109" height="109" viewBox="0 0 109 109">
+<g id="kvg:StrokePaths_04eba" style="fill:none;stroke:#000000;stroke-width:3;stroke-linecap:round;stroke-linejoin:round;">
+<g id="kvg:04eba" kvg:element="人" kvg:radical="general">
+	<path id="kvg:04eba-s1" kvg:type="㇒" d="M54.5,14.5c0.12,1.25,0.23,4.3-0.24,6.68C51.5,35,40.25,69,14.5,86.5"/>
+	<path id="kvg:04eba-s2" kvg:type="㇏" d="M49.75,42.25c7.41,10.88,20.42,33.37,29.5,40.07c3.35,2.47,5.9,3.23,10.5,3.93"/>
+</g>
+</g>
+<g id="kvg:StrokeNumbers_04eba" style="font-size:8;fill:#808080">
+	<text transform="matrix(1 0 0 1 45.50 15.50)">1</text>
+	<text transform="matrix(1 0 0 1 57.50 47.50)">2</text>
+</g>
+</svg>"#;
+
+    #[test]
+    fn test_parse_kanjivg() {
+        let stroke_order = parse_kanjivg('人', KANJIVG_SAMPLE).unwrap();
+        assert_eq!(stroke_order.stroke_count(), 2);
+        assert_eq!(stroke_order.numbers, vec![(45.5, 15.5), (57.5, 47.5)]);
+        assert!(stroke_order.strokes[1].starts_with("M49.75,42.25"));
+    }
+
+    #[test]
+    fn test_svg_draws_the_first_strokes() {
+        let stroke_order = parse_kanjivg('人', KANJIVG_SAMPLE).unwrap();
+        let svg = stroke_order.svg(1, true);
+        assert_eq!(svg.matches(CURRENT_COLOR).count(), 1);
+        assert_eq!(svg.matches(UPCOMING_COLOR).count(), 1);
+        assert_eq!(svg.matches("<text").count(), 1);
+
+        let svg = stroke_order.svg(2, false);
+        assert_eq!(svg.matches(DRAWN_COLOR).count(), 2);
+        assert_eq!(svg.matches("<text").count(), 2);
+        assert!(roxmltree::Document::parse(&svg).is_ok());
+    }
+
+    #[test]
+    fn test_file_names() {
+        assert_eq!(
+            kanjivg_file('猫'),
+            PathBuf::from("resources/kanjivg/0732b.svg")
+        );
+        assert_eq!(media_file_name('猫'), "kanjivg_0732b.svg");
+    }
+}
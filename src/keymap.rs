@@ -0,0 +1,458 @@
+use iced::keyboard::{KeyCode, Modifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Confirm,
+    Back,
+    Forward,
+    Quit,
+    Next,
+    Previous,
+    ToggleTranslation,
+    ToggleFurigana,
+    SaveCard,
+    FocusSearch,
+    Help,
+}
+
+impl Action {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Confirm => "Search / open the selected word / show answer",
+            Action::Back => "Go back",
+            Action::Forward => "Go forward again",
+            Action::Quit => "Quit",
+            Action::Next => "Select next",
+            Action::Previous => "Select previous",
+            Action::ToggleTranslation => "Show or hide translations",
+            Action::ToggleFurigana => "Show or hide furigana",
+            Action::SaveCard => "Save the selected sentence as a flash card",
+            Action::FocusSearch => "Go to the search box",
+            Action::Help => "Show this help",
+        }
+    }
+}
+
+// Bindings can be limited to one screen, those take precedence over the global ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Screen {
+    Search,
+    Loading,
+    Results,
+    Sentences,
+    Grammar,
+    Analysis,
+    Details,
+    PendingCards,
+    Batch,
+    Favorites,
+    Study,
+    Help,
+}
+
+impl Screen {
+    pub const ALL: [Screen; 12] = [
+        Screen::Search,
+        Screen::Loading,
+        Screen::Results,
+        Screen::Sentences,
+        Screen::Grammar,
+        Screen::Analysis,
+        Screen::Details,
+        Screen::PendingCards,
+        Screen::Batch,
+        Screen::Favorites,
+        Screen::Study,
+        Screen::Help,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Screen::Search => "Search",
+            Screen::Loading => "Loading",
+            Screen::Results => "Search results",
+            Screen::Sentences => "Sentence search",
+            Screen::Grammar => "Grammar",
+            Screen::Analysis => "Text analysis",
+            Screen::Details => "Details",
+            Screen::PendingCards => "Pending cards",
+            Screen::Batch => "Cards from a word list",
+            Screen::Favorites => "Favorites",
+            Screen::Study => "Study",
+            Screen::Help => "Help",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub key_code: KeyCode,
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+}
+
+impl KeyBinding {
+    fn matches(&self, key_code: KeyCode, modifiers: Modifiers) -> bool {
+        self.key_code == key_code
+            && self.shift == modifiers.shift()
+            && self.control == modifiers.control()
+            && self.alt == modifiers.alt()
+    }
+
+    // Ctrl and Alt shortcuts still work while typing in a text box.
+    fn is_shortcut(&self) -> bool {
+        self.control || self.alt
+    }
+}
+
+const DEFAULT_BINDINGS: [(&str, Action, Option<Screen>); 22] = [
+    ("Enter", Action::Confirm, None),
+    ("NumpadEnter", Action::Confirm, None),
+    ("Escape", Action::Back, None),
+    ("Alt+Left", Action::Back, None),
+    ("Alt+Right", Action::Forward, None),
+    ("Q", Action::Quit, None),
+    ("Ctrl+Q", Action::Quit, None),
+    ("Down", Action::Next, None),
+    ("J", Action::Next, None),
+    ("Up", Action::Previous, None),
+    ("K", Action::Previous, None),
+    ("/", Action::FocusSearch, None),
+    ("Ctrl+F", Action::FocusSearch, None),
+    ("Ctrl+L", Action::FocusSearch, None),
+    ("Shift+/", Action::Help, None),
+    ("F1", Action::Help, None),
+    ("T", Action::ToggleTranslation, Some(Screen::Details)),
+    ("F", Action::ToggleFurigana, Some(Screen::Details)),
+    ("S", Action::SaveCard, Some(Screen::Details)),
+    ("Ctrl+S", Action::SaveCard, Some(Screen::Details)),
+    ("T", Action::ToggleTranslation, Some(Screen::Study)),
+    ("Space", Action::ToggleTranslation, Some(Screen::Study)),
+];
+
+#[derive(Debug, Clone)]
+struct Binding {
+    key: String,
+    binding: KeyBinding,
+    action: Action,
+    screen: Option<Screen>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    // The default bindings, where every action that shows up in the overrides of a
+    // screen (key -> action, from the config file) only gets the user's keys there, and
+    // keys the user bound to something else on that screen lose their default.
+    // `global` overrides apply to every screen.
+    pub fn new(
+        global: &BTreeMap<String, Action>,
+        per_screen: &BTreeMap<Screen, BTreeMap<String, Action>>,
+    ) -> Self {
+        let user = global
+            .iter()
+            .map(|(key, action)| (key.clone(), *action, None))
+            .chain(per_screen.iter().flat_map(|(screen, overrides)| {
+                overrides
+                    .iter()
+                    .map(|(key, action)| (key.clone(), *action, Some(*screen)))
+            }));
+        let mut bindings: Vec<Binding> = Vec::new();
+        for (key, action, screen) in user {
+            match parse_key_binding(&key) {
+                Some(binding) => bindings.push(Binding {
+                    key,
+                    binding,
+                    action,
+                    screen,
+                }),
+                None => eprintln!("config: unknown key \"{}\", ignoring it", key),
+            }
+        }
+
+        let overrides = |screen: Option<Screen>| match screen {
+            None => Some(global),
+            Some(screen) => per_screen.get(&screen),
+        };
+        for (key, action, screen) in DEFAULT_BINDINGS {
+            if overrides(screen).is_some_and(|o| o.values().any(|a| *a == action)) {
+                continue;
+            }
+            let Some(binding) = parse_key_binding(key) else {
+                continue;
+            };
+            let taken = bindings
+                .iter()
+                .any(|user| user.screen == screen && user.binding == binding);
+            if !taken {
+                bindings.push(Binding {
+                    key: key.to_string(),
+                    binding,
+                    action,
+                    screen,
+                });
+            }
+        }
+        Keymap { bindings }
+    }
+
+    // `captured` is set when a widget (e.g. the search box) already handled the key,
+    // only Ctrl/Alt shortcuts get through then.
+    pub fn action(
+        &self,
+        screen: Screen,
+        key_code: KeyCode,
+        modifiers: Modifiers,
+        captured: bool,
+    ) -> Option<Action> {
+        let matching = |b: &&Binding| {
+            b.binding.matches(key_code, modifiers) && (!captured || b.binding.is_shortcut())
+        };
+        self.bindings
+            .iter()
+            .filter(|b| b.screen == Some(screen))
+            .find(matching)
+            .or_else(|| {
+                self.bindings
+                    .iter()
+                    .filter(|b| b.screen.is_none())
+                    .find(matching)
+            })
+            .map(|b| b.action)
+    }
+
+    // Every action with the keys bound to it on `screen` (or everywhere when it's
+    // None), for the help screen.
+    pub fn describe(&self, screen: Option<Screen>) -> Vec<(Action, Vec<String>)> {
+        let mut keys_per_action: BTreeMap<Action, Vec<String>> = BTreeMap::new();
+        for binding in self.bindings.iter().filter(|b| b.screen == screen) {
+            keys_per_action
+                .entry(binding.action)
+                .or_default()
+                .push(binding.key.clone());
+        }
+        keys_per_action.into_iter().collect()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::new(&BTreeMap::new(), &BTreeMap::new())
+    }
+}
+
+// Parses keys like "J", "Enter", "F1" or "Shift+/", case insensitive.
+pub fn parse_key_binding(key: &str) -> Option<KeyBinding> {
+    let mut parts: Vec<&str> = key.split('+').collect();
+    // "Ctrl++" binds the plus key
+    if key.ends_with("++") {
+        parts.pop();
+        parts.pop();
+        parts.push("+");
+    }
+    let (key_name, modifiers) = parts.split_last()?;
+    let mut binding = KeyBinding {
+        key_code: parse_key_code(key_name)?,
+        shift: false,
+        control: false,
+        alt: false,
+    };
+    for modifier in modifiers {
+        match modifier.to_lowercase().as_str() {
+            "shift" => binding.shift = true,
+            "ctrl" | "control" => binding.control = true,
+            "alt" => binding.alt = true,
+            _ => return None,
+        }
+    }
+    Some(binding)
+}
+
+fn parse_key_code(name: &str) -> Option<KeyCode> {
+    use KeyCode::*;
+    const LETTERS: [KeyCode; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+    const DIGITS: [KeyCode; 10] = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    const NUMPAD: [KeyCode; 10] = [
+        Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    ];
+    const FUNCTION_KEYS: [KeyCode; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+
+    let lowercase = name.to_lowercase();
+    let mut chars = lowercase.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            'a'..='z' => Some(LETTERS[c as usize - 'a' as usize]),
+            '0'..='9' => Some(DIGITS[c as usize - '0' as usize]),
+            '/' => Some(Slash),
+            '-' => Some(Minus),
+            '=' => Some(Equals),
+            '+' => Some(Plus),
+            ',' => Some(Comma),
+            '.' => Some(Period),
+            ';' => Some(Semicolon),
+            '\'' => Some(Apostrophe),
+            '[' => Some(LBracket),
+            ']' => Some(RBracket),
+            '\\' => Some(Backslash),
+            '`' => Some(Grave),
+            _ => None,
+        };
+    }
+    if let Some(n) = lowercase.strip_prefix("numpad") {
+        if let Ok(n) = n.parse::<usize>() {
+            return NUMPAD.get(n).copied();
+        }
+    }
+    if let Some(n) = lowercase.strip_prefix('f') {
+        if let Ok(n) = n.parse::<usize>() {
+            return FUNCTION_KEYS.get(n.wrapping_sub(1)).copied();
+        }
+    }
+    match lowercase.as_str() {
+        "enter" | "return" => Some(Enter),
+        "numpadenter" => Some(NumpadEnter),
+        "escape" | "esc" => Some(Escape),
+        "space" => Some(Space),
+        "tab" => Some(Tab),
+        "backspace" => Some(Backspace),
+        "delete" => Some(Delete),
+        "insert" => Some(Insert),
+        "home" => Some(Home),
+        "end" => Some(End),
+        "pageup" => Some(PageUp),
+        "pagedown" => Some(PageDown),
+        "up" => Some(Up),
+        "down" => Some(Down),
+        "left" => Some(Left),
+        "right" => Some(Right),
+        _ => None,
+    }
+}
+
+// Number keys pick from a list (e.g. example sentences), so they aren't remappable.
+pub fn number_key(key_code: KeyCode) -> Option<usize> {
+    use KeyCode::*;
+    match key_code {
+        Key1 | Numpad1 => Some(1),
+        Key2 | Numpad2 => Some(2),
+        Key3 | Numpad3 => Some(3),
+        Key4 | Numpad4 => Some(4),
+        Key5 | Numpad5 => Some(5),
+        Key6 | Numpad6 => Some(6),
+        Key7 | Numpad7 => Some(7),
+        Key8 | Numpad8 => Some(8),
+        Key9 | Numpad9 => Some(9),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key_binding() {
+        assert_eq!(
+            parse_key_binding("Shift+/"),
+            Some(KeyBinding {
+                key_code: KeyCode::Slash,
+                shift: true,
+                control: false,
+                alt: false,
+            })
+        );
+        assert_eq!(
+            parse_key_binding("ctrl++").map(|b| (b.key_code, b.control)),
+            Some((KeyCode::Plus, true))
+        );
+        assert_eq!(parse_key_binding("j").map(|b| b.key_code), Some(KeyCode::J));
+        assert_eq!(
+            parse_key_binding("F12").map(|b| b.key_code),
+            Some(KeyCode::F12)
+        );
+        assert_eq!(parse_key_binding("Hyper+J"), None);
+        assert_eq!(parse_key_binding("F13"), None);
+    }
+
+    #[test]
+    fn test_overrides_replace_default_keys_of_an_action() {
+        let overrides = BTreeMap::from([("N".to_string(), Action::Next)]);
+        let keymap = Keymap::new(&overrides, &BTreeMap::new());
+        let none = Modifiers::empty();
+        let action = |key_code| keymap.action(Screen::Results, key_code, none, false);
+        assert_eq!(action(KeyCode::N), Some(Action::Next));
+        assert_eq!(action(KeyCode::J), None);
+        assert_eq!(action(KeyCode::Down), None);
+        assert_eq!(action(KeyCode::K), Some(Action::Previous));
+    }
+
+    #[test]
+    fn test_overrides_take_the_key_from_its_default() {
+        let overrides = BTreeMap::from([("J".to_string(), Action::Previous)]);
+        let keymap = Keymap::new(&overrides, &BTreeMap::new());
+        let none = Modifiers::empty();
+        let action = |key_code| keymap.action(Screen::Results, key_code, none, false);
+        assert_eq!(action(KeyCode::J), Some(Action::Previous));
+        assert_eq!(action(KeyCode::Down), Some(Action::Next));
+        let next_keys = keymap
+            .describe(None)
+            .into_iter()
+            .find(|(action, _)| *action == Action::Next)
+            .map(|(_, keys)| keys);
+        assert_eq!(next_keys, Some(vec!["Down".to_string()]));
+    }
+
+    #[test]
+    fn test_screen_bindings() {
+        let study = BTreeMap::from([("N".to_string(), Action::ToggleTranslation)]);
+        let keymap = Keymap::new(&BTreeMap::new(), &BTreeMap::from([(Screen::Study, study)]));
+        let none = Modifiers::empty();
+        assert_eq!(
+            keymap.action(Screen::Details, KeyCode::S, none, false),
+            Some(Action::SaveCard)
+        );
+        assert_eq!(
+            keymap.action(Screen::Results, KeyCode::S, none, false),
+            None
+        );
+        assert_eq!(
+            keymap.action(Screen::Study, KeyCode::N, none, false),
+            Some(Action::ToggleTranslation)
+        );
+        assert_eq!(keymap.action(Screen::Study, KeyCode::T, none, false), None);
+        // global keys work everywhere
+        assert_eq!(
+            keymap.action(Screen::Study, KeyCode::Escape, none, false),
+            Some(Action::Back)
+        );
+    }
+
+    #[test]
+    fn test_only_shortcuts_while_typing() {
+        let keymap = Keymap::default();
+        let none = Modifiers::empty();
+        assert_eq!(keymap.action(Screen::Search, KeyCode::Q, none, true), None);
+        assert_eq!(
+            keymap.action(Screen::Search, KeyCode::Enter, none, true),
+            None
+        );
+        assert_eq!(
+            keymap.action(Screen::Search, KeyCode::Q, Modifiers::CTRL, true),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action(Screen::Search, KeyCode::Q, none, false),
+            Some(Action::Quit)
+        );
+    }
+}
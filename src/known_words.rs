@@ -0,0 +1,141 @@
+// The words the user already knows, to find sentences with just one new word in them (i+1).
+use crate::anki_import::AnkiVocabulary;
+use crate::example_sentences::ExampleSentence;
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::prelude::*;
+
+pub const KNOWN_WORDS_FILE: &str = "known_words.json";
+
+// Particles and the like are indexed in the sentences too, but nobody needs to learn them
+// to read a sentence.
+pub const FUNCTION_WORDS: [&str; 20] = [
+    "は", "が", "を", "に", "で", "と", "も", "の", "へ", "から", "まで", "より", "や", "か",
+    "です", "だ", "ます", "する", "いる", "ある",
+];
+
+pub type KnownWords = HashSet<String>;
+
+pub fn load_known_words() -> KnownWords {
+    std::fs::read_to_string(KNOWN_WORDS_FILE)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn store_known_words(known_words: &KnownWords) -> Result<(), Box<dyn Error>> {
+    let mut words: Vec<&String> = known_words.iter().collect();
+    words.sort();
+    let mut file = std::fs::File::create(KNOWN_WORDS_FILE)?;
+    file.write_all(serde_json::to_string_pretty(&words)?.as_bytes())?;
+    Ok(())
+}
+
+// Marks a word as known, or unmarks it if it was known already.
+pub fn toggle(known_words: &mut KnownWords, word: &str) {
+    if !known_words.remove(word) {
+        known_words.insert(word.to_string());
+    }
+}
+
+// Adds the words of the Anki decks the user knows, returns how many of them are new.
+pub fn add_from_anki(known_words: &mut KnownWords, vocabulary: &AnkiVocabulary) -> usize {
+    let before = known_words.len();
+    known_words.extend(
+        vocabulary
+            .iter()
+            .filter(|(_, anki_word)| anki_word.is_known())
+            .map(|(word, _)| word.clone()),
+    );
+    known_words.len() - before
+}
+
+// The words of the sentence the user still has to learn, each once.
+pub fn unknown_words<'a>(sentence: &'a ExampleSentence, known_words: &KnownWords) -> Vec<&'a str> {
+    let mut unknown: Vec<&str> = Vec::new();
+    for index_word in &sentence.indices {
+        let word = index_word.headword.as_str();
+        if !known_words.contains(word)
+            && !FUNCTION_WORDS.contains(&word)
+            && !unknown.contains(&word)
+        {
+            unknown.push(word);
+        }
+    }
+    unknown
+}
+
+// Whether the target is the only word of the sentence the user doesn't know yet.
+pub fn is_i_plus_one(sentence: &ExampleSentence, target: &str, known_words: &KnownWords) -> bool {
+    unknown_words(sentence, known_words) == [target]
+}
+
+// The shortest sentence where the target is the only new word.
+pub fn i_plus_one_sentence<'a>(
+    sentences: &'a [ExampleSentence],
+    target: &str,
+    known_words: &KnownWords,
+) -> Option<&'a ExampleSentence> {
+    sentences
+        .iter()
+        .filter(|sentence| is_i_plus_one(sentence, target, known_words))
+        .min_by_key(|sentence| sentence.japanese_text.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::example_sentences::IndexWord;
+
+    fn sentence(japanese: &str, headwords: &[&str]) -> ExampleSentence {
+        ExampleSentence {
+            japanese_text: japanese.to_string(),
+            indices: headwords
+                .iter()
+                .map(|headword| IndexWord {
+                    headword: headword.to_string(),
+                    reading: None,
+                    sense_number: None,
+                    form_in_sentence: None,
+                    good_and_checked: false,
+                })
+                .collect(),
+            ..ExampleSentence::default()
+        }
+    }
+
+    #[test]
+    fn test_i_plus_one() {
+        let known_words: KnownWords = ["猫", "好き"].iter().map(|word| word.to_string()).collect();
+        let sentences = vec![
+            sentence("猫が好きです。", &["猫", "が", "好き", "です"]),
+            sentence(
+                "黒い犬と猫が好きです。",
+                &["黒い", "犬", "と", "猫", "が", "好き", "です"],
+            ),
+            sentence("犬が好きです。", &["犬", "が", "好き", "です"]),
+            sentence("犬は犬だ。", &["犬", "は", "犬", "だ"]),
+        ];
+        assert!(unknown_words(&sentences[0], &known_words).is_empty());
+        assert_eq!(
+            unknown_words(&sentences[1], &known_words),
+            vec!["黒い", "犬"]
+        );
+        assert!(!is_i_plus_one(&sentences[1], "犬", &known_words));
+        assert!(is_i_plus_one(&sentences[2], "犬", &known_words));
+        assert_eq!(
+            i_plus_one_sentence(&sentences, "犬", &known_words),
+            Some(&sentences[3])
+        );
+        assert_eq!(i_plus_one_sentence(&sentences, "猫", &known_words), None);
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut known_words = KnownWords::new();
+        toggle(&mut known_words, "猫");
+        assert!(known_words.contains("猫"));
+        toggle(&mut known_words, "猫");
+        assert!(known_words.is_empty());
+    }
+}
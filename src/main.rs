@@ -1,55 +1,253 @@
 use iced::alignment::Horizontal;
-use iced::widget::{scrollable, slider, Button, Column, Container, Row, Space, Text, TextInput};
+use iced::widget::{
+    scrollable, slider, svg, text_input, Button, Checkbox, Column, Container, PickList, Row, Space,
+    Svg, Text, TextInput,
+};
 use iced::{
-    keyboard, window, Alignment, Application, Color, Command, Element, Length, Settings,
-    Subscription,
+    keyboard, mouse, theme, time, window, Alignment, Application, Color, Command, Element, Length,
+    Settings, Subscription,
 };
 
-use std::error::Error;
 mod jisho;
 use crate::jisho::JishoResponse;
 mod example_sentences;
-use crate::example_sentences::{wwwjdict_parser, ExampleSentence};
+use crate::example_sentences::{split_on_index_words, wwwjdict_parser, ExampleSentence};
+mod flashcards;
+use crate::flashcards::{Flashcard, FlashcardField};
+mod apkg;
+mod furigana;
+use crate::furigana::{katakana_to_hiragana, FuriganaSegment};
+mod srs;
+use crate::srs::{Grade, StudyCard};
+mod history;
+use crate::history::HistoryEntry;
+mod favorites;
+use crate::favorites::{Favorite, FavoritesSort};
+mod keymap;
+use crate::keymap::{Action, Keymap, Screen};
+mod config;
+use crate::config::WindowGeometry;
+mod navigation;
+use crate::navigation::{Navigation, Page};
+mod kanjidic;
+use crate::kanjidic::{KanjiEntry, Kanjidic};
+mod kanjivg;
+use crate::kanjivg::StrokeOrder;
+mod radicals;
+mod romaji;
+use crate::romaji::{InputMode, RomajiDisplay};
+mod difficulty;
+mod grammar;
+mod ranking;
+mod sentence_index;
+use crate::difficulty::{Level, SentenceOrder, SentencePolicy};
+mod known_words;
+use crate::known_words::KnownWords;
+mod anki_import;
+use crate::anki_import::{AnkiImport, AnkiVocabulary};
+mod batch;
+use crate::batch::{BatchEntry, Issue};
+mod deinflect;
+mod text_analysis;
+use crate::text_analysis::Analysis;
+mod cli;
+mod clipboard;
+use crate::clipboard::{ClipboardConfig, ClipboardWatcher, SystemClipboard};
+use crate::grammar::CompiledPattern;
+use crate::radicals::Radicals;
+use crate::ranking::{JlptFilter, Ranking, ResultsOrder, ResultsSort};
+use crate::sentence_index::{SentenceIndex, SentenceMatch};
 extern crate nom;
 use rayon::prelude::*;
-use serde::Serialize;
-use std::collections::HashMap;
-use std::io::prelude::*;
+use rusqlite::Connection;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use iced_aw::{Card, Modal};
 
-use iced_native::{subscription, Event};
+use iced_native::{event, subscription, Event};
+
+const SEARCH_INPUT: &str = "search";
+const SEARCH_RESULTS: &str = "search-results";
+const MAX_SENTENCES: usize = 20;
+const MAX_SENTENCE_MATCHES: usize = 100;
+const SENTENCE_MATCHES: &str = "sentence-matches";
+const MAX_GRAMMAR_SENTENCES: usize = 50;
+const GRAMMAR_PATTERNS: &str = "grammar-patterns";
+const STROKE_ANIMATION_INTERVAL: time::Duration = time::Duration::from_millis(600);
 
 #[derive(Debug)]
 enum Dict {
     Startup {},
     Waiting {
         input_value: String,
-        example_sentences: SentenceMap,
+        // the radicals picked so far, None while the picker is closed
+        radical_picker: Option<Vec<char>>,
+        due_count: usize,
+        history_cursor: Option<usize>,
         show_modal: bool,
+        status: String,
     },
     Loading {
-        example_sentences: SentenceMap,
+        query: String,
+        // a word clicked in a sentence, its details are opened right away
+        open_details: bool,
+        // the number of the search, the answer to an earlier one is ignored
+        search: u64,
     },
     Loaded {
         search_results: Vec<SearchResult>,
-        example_sentences: SentenceMap,
+        selected: usize,
+    },
+    Sentences {
+        query: String,
+        matches: Vec<SentenceMatch>,
+        selected: usize,
+    },
+    Grammar {
+        // index into the grammar patterns
+        selected: usize,
+        matches: Vec<SentenceMatch>,
+        selected_sentence: usize,
+        status: String,
+    },
+    Analysis {
+        text: String,
+        analysis: Analysis,
+        // words of the vocabulary list to make cards for
+        selected: HashSet<String>,
+        show_known: bool,
+        status: String,
     },
     Details {
         word: String,
         reading: String,
         translations: Vec<String>,
         toggle_show_translation: bool,
+        show_furigana: bool,
         search_results: Vec<SearchResult>,
-        example_sentences: SentenceMap,
         text_zoom_value: u16,
+        // the example sentences as they're listed, with their difficulty and whether
+        // they're i+1; worked out again when the order or the known words change
+        sentences: Vec<(ExampleSentence, u32, bool)>,
+        selected_sentence: Option<usize>,
         show_modal: bool,
+        stroke_orders: Vec<StrokeOrder>,
+        // how many strokes of the whole word are drawn, None shows the finished diagrams
+        stroke_step: Option<usize>,
+        animate_strokes: bool,
+    },
+    Pending {
+        status: String,
+        stroke_order_media: bool,
+    },
+    Batch {
+        file: String,
+        flagged_only: bool,
+        status: String,
+    },
+    Favorites {
+        filter: String,
+        sort: FavoritesSort,
+        // (word, reading) of the selected favorites
+        selected: HashSet<(String, String)>,
+        status: String,
     },
+    Study {
+        queue: Vec<StudyCard>,
+        toggle_show_translation: bool,
+    },
+    Help {},
+}
+
+// What every screen works with, kept while moving between them.
+#[derive(Debug, Default)]
+struct Data {
+    example_sentences: SentenceMap,
+    pending_cards: Vec<Flashcard>,
+    favorites: Vec<Favorite>,
+    history: Vec<HistoryEntry>,
+    keymap: Keymap,
+    kanjidic: Kanjidic,
+    // the kanjidic couldn't be read, the details page says so instead of the kanji panel
+    kanjidic_missing: bool,
+    radicals: Radicals,
+    // the radkfile couldn't be read, so the radical picker can't be opened
+    radicals_missing: bool,
+    input_mode: InputMode,
+    romaji_display: RomajiDisplay,
+    sentence_index: SentenceIndex,
+    search_tab: SearchTab,
+    grammar: Vec<CompiledPattern>,
+    results_order: ResultsOrder,
+    sentence_order: SentenceOrder,
+    known_words: KnownWords,
+    anki_import: AnkiImport,
+    anki_vocabulary: AnkiVocabulary,
+    sentence_policy: SentencePolicy,
+    card_furigana: bool,
+    // the word list of the batch screen, kept so lookups carry on on other screens
+    batch: Vec<BatchEntry>,
+    // a word of the batch is being looked up, its answer looks up the next one
+    batch_looking_up: bool,
+    // the number of the last search sent to jisho
+    last_search: u64,
+    // the study deck, None when the database can't be opened
+    study_database: Option<Connection>,
+    clipboard: ClipboardConfig,
+    // why the clipboard can't be watched, shown next to the checkbox
+    clipboard_error: Option<String>,
+}
+
+// What the search screen looks up: words on jisho, or the local example sentences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SearchTab {
+    #[default]
+    Words,
+    Sentences,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     FoundExampleSentences(Result<String, DictError>),
+    KanjidicLoaded(Result<Kanjidic, DictError>),
+    KanjiPressed(char),
+    RadicalsLoaded(Result<Radicals, DictError>),
+    RadicalsButtonPressed,
+    RadicalPressed(char),
+    ClearRadicalsButtonPressed,
+    RadicalKanjiPressed(char),
+    InputModeSelected(InputMode),
+    WatchClipboardToggled(bool),
+    ClipboardTick,
+    RomajiDisplaySelected(RomajiDisplay),
+    SearchTabSelected(SearchTab),
+    ResultsSortPressed(ResultsSort),
+    CommonOnlyToggled(bool),
+    JlptFilterSelected(JlptFilter),
+    SentenceOrderSelected(SentenceOrder),
+    ToggleKnownButtonPressed(String),
+    ImportAnkiButtonPressed,
+    BatchButtonPressed,
+    BatchFileChanged(String),
+    LoadBatchButtonPressed,
+    BatchWordFound(String, Result<JishoResponse, DictError>),
+    FlaggedOnlyToggled(bool),
+    NextBatchEntry(usize),
+    BatchCardEdited(usize, FlashcardField, String),
+    SwapBatchCardSentence(usize),
+    DeleteBatchEntry(usize),
+    AddBatchCardsButtonPressed,
+    AnalysisButtonPressed,
+    AnalysisTextChanged(String),
+    AnalyzeButtonPressed,
+    ShowKnownToggled(bool),
+    AnalysisWordSelected(String, bool),
+    AnalysisCardsButtonPressed,
+    GrammarButtonPressed,
+    GrammarPatternSelected(usize),
+    GrammarSentenceSelected(usize),
+    GrammarCardButtonPressed(usize),
+    GrammarCardsButtonPressed,
     InputChanged(String),
     SearchButtonPressed,
     BackButtonPressed,
@@ -59,7 +257,9 @@ enum Message {
     EscapeButtonPressed,
     QButtonPressed,
     TButtonPressed,
-    WordFound(Result<JishoResponse, DictError>),
+    FButtonPressed,
+    ToggleFuriganaButtonPressed,
+    WordFound(u64, Result<JishoResponse, DictError>),
     SearchAgainButtonPressed,
     TextSizeSliderChanged(u16),
     OpenModal,
@@ -67,6 +267,50 @@ enum Message {
     CancelButtonPressed,
     OkButtonPressed,
     UndoButtonPressed,
+    PendingCardsButtonPressed,
+    PendingCardEdited(usize, FlashcardField, String),
+    MovePendingCardUp(usize),
+    MovePendingCardDown(usize),
+    SwapPendingCardSentence(usize),
+    DeletePendingCard(usize),
+    ExportCsvButtonPressed,
+    ExportApkgButtonPressed,
+    StrokeOrderMediaToggled(bool),
+    AnimateStrokesButtonPressed,
+    PreviousStrokeButtonPressed,
+    NextStrokeButtonPressed,
+    StrokeAnimationTick,
+    // the word the diagrams are for, the details page may show another one by now
+    StrokeOrdersLoaded(String, Vec<StrokeOrder>),
+    ExportAnkiConnectButtonPressed,
+    // how many cards were sent, the ones Anki added
+    AnkiConnectExported(usize, Result<Vec<Flashcard>, DictError>),
+    StudyButtonPressed,
+    GradeButtonPressed(Grade),
+    NumberKeyPressed(usize),
+    UpButtonPressed,
+    DownButtonPressed,
+    HistoryEntryPressed(String),
+    ClearHistoryButtonPressed,
+    ToggleFavoriteButtonPressed(String, String, Vec<String>),
+    FavoritesButtonPressed,
+    FavoritesFilterChanged(String),
+    FavoritesSortSelected(FavoritesSort),
+    // word, reading
+    FavoriteSelected(String, String, bool),
+    SelectAllFavoritesButtonPressed,
+    ExportFavoritesButtonPressed,
+    EnterButtonPressed,
+    SaveCardButtonPressed,
+    FocusSearchButtonPressed,
+    HelpButtonPressed,
+    ForwardButtonPressed,
+    SentenceWordPressed(String, Option<String>),
+    KeyPressed(keyboard::KeyCode, keyboard::Modifiers, event::Status),
+    WindowResized(u32, u32),
+    WindowMoved(i32, i32),
+    CloseRequested,
+    QuitConfirmed,
 }
 
 #[derive(Debug, Clone)]
@@ -74,19 +318,21 @@ struct SearchResult {
     pub japanese: String,
     pub reading: String,
     pub translations: Vec<String>,
+    pub ranking: Ranking,
 }
 
 type SentenceMap = HashMap<String, Vec<ExampleSentence>>;
 
 impl SearchResult {
-    fn new(japanese: String, reading: String, translations: Vec<String>) -> Self {
+    fn new(japanese: String, reading: String, translations: Vec<String>, ranking: Ranking) -> Self {
         Self {
             japanese,
             reading,
             translations,
+            ranking,
         }
     }
-    fn _to_row(&self) -> Row<Message> {
+    fn _to_row(&self) -> Row<'_, Message> {
         Row::new()
             .spacing(10)
             .push(Text::new(&self.japanese).size(30).width(Length::Fill))
@@ -100,20 +346,361 @@ impl SearchResult {
     }
 }
 
+fn search_results(
+    jisho_result: &JishoResponse,
+    example_sentences: &SentenceMap,
+) -> Vec<SearchResult> {
+    let mut search_results: Vec<SearchResult> = vec![];
+    for i in &jisho_result.data {
+        let japanese = &i.slug;
+        let reading = &i.japanese[0].reading.clone().unwrap_or_default();
+        let translation = &i.senses[0].english_definitions;
+        let ranking = Ranking {
+            is_common: i.is_common.unwrap_or_default(),
+            jlpt: ranking::jlpt_level(&i.jlpt),
+            frequency: example_sentences
+                .get(japanese)
+                .map(Vec::len)
+                .unwrap_or_default(),
+        };
+        search_results.push(SearchResult::new(
+            japanese.clone(),
+            reading.clone(),
+            translation.clone(),
+            ranking,
+        ));
+    }
+    search_results
+}
+
 pub fn main() -> iced::Result {
-    Dict::run(Settings {
+    // with arguments it's the command line version, see cli.rs
+    if std::env::args_os().len() > 1 {
+        std::process::exit(cli::run());
+    }
+    let geometry = config::load_window_geometry();
+    App::run(Settings {
         default_font: Some(include_bytes!("../resources/Meiryo.ttf")),
         window: window::Settings {
-            size: (800, 600),
+            size: (geometry.width, geometry.height),
+            position: match (geometry.x, geometry.y) {
+                (Some(x), Some(y)) => window::Position::Specific(x, y),
+                _ => window::Position::default(),
+            },
             resizable: true,
             decorations: true,
             ..window::Settings::default()
         },
         antialiasing: true,
+        // closing goes through `App::update`, which saves everything first
+        exit_on_close_request: false,
+        flags: geometry,
         ..Settings::default()
     })
 }
 
+// The current screen, the ones to go back to, and what has to outlive them.
+struct App {
+    screen: Dict,
+    navigation: Navigation<Dict>,
+    data: Data,
+    window: WindowGeometry,
+    // watching the clipboard, if that's turned on
+    clipboard: Option<ClipboardWatcher<SystemClipboard>>,
+}
+
+impl Application for App {
+    type Executor = iced::executor::Default;
+    type Message = Message;
+    type Theme = iced::Theme;
+    type Flags = WindowGeometry;
+
+    fn new(window: WindowGeometry) -> (Self, Command<Message>) {
+        let config = config::load_config();
+        let mut data = Data {
+            pending_cards: flashcards::load_pending_cards(),
+            study_database: srs::open_database().ok(),
+            favorites: favorites::load_favorites(),
+            history: history::load_history(),
+            keymap: Keymap::new(&config.key_bindings, &config.screen_key_bindings),
+            input_mode: config.input_mode,
+            romaji_display: config.romaji,
+            grammar: grammar::load_patterns(),
+            known_words: known_words::load_known_words(),
+            anki_import: config.anki_import,
+            anki_vocabulary: anki_import::load_vocabulary(),
+            sentence_policy: config.sentence_policy,
+            card_furigana: config.card_furigana,
+            clipboard: ClipboardConfig {
+                enabled: clipboard::load_watching().unwrap_or(config.clipboard.enabled),
+                ..config.clipboard
+            },
+            ..Data::default()
+        };
+        let clipboard = watch_clipboard(&mut data);
+        (
+            App {
+                screen: Dict::Startup {},
+                navigation: Navigation::default(),
+                data,
+                window,
+                clipboard,
+            },
+            Command::batch(vec![
+                Command::perform(
+                    Dict::load_example_sentences(),
+                    Message::FoundExampleSentences,
+                ),
+                Command::perform(Dict::load_kanjidic(), Message::KanjidicLoaded),
+                Command::perform(Dict::load_radicals(), Message::RadicalsLoaded),
+            ]),
+        )
+    }
+
+    fn title(&self) -> String {
+        self.screen.title()
+    }
+
+    fn update(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::KeyPressed(key_code, modifiers, status) => {
+                match self.handle_hotkey(key_code, modifiers, status) {
+                    Some(message) => self.update(message),
+                    None => Command::none(),
+                }
+            }
+            Message::FoundExampleSentences(Ok(sentences)) => {
+                (self.data.example_sentences, self.data.sentence_index) =
+                    Dict::parse_example_sentences(sentences);
+                println!("startup: finished loading sentences!");
+                self.screen = Dict::waiting(&self.data);
+                Command::none()
+            }
+            Message::FoundExampleSentences(Err(_error)) => {
+                // loading/parsing sentences somehow failed
+                // TODO: do something
+                Command::none()
+            }
+            Message::KanjidicLoaded(result) => {
+                match result {
+                    Ok(kanjidic) => self.data.kanjidic = kanjidic,
+                    Err(_) => self.data.kanjidic_missing = true,
+                }
+                Command::none()
+            }
+            Message::RadicalsLoaded(result) => {
+                match result {
+                    Ok(radicals) => self.data.radicals = radicals,
+                    Err(_) => self.data.radicals_missing = true,
+                }
+                Command::none()
+            }
+            Message::InputModeSelected(input_mode) => {
+                self.data.input_mode = input_mode;
+                if let Dict::Waiting { input_value, .. } = &mut self.screen {
+                    *input_value = romaji::to_kana(input_value, input_mode, false);
+                }
+                text_input::focus(text_input::Id::new(SEARCH_INPUT))
+            }
+            Message::WatchClipboardToggled(checked) => {
+                self.data.clipboard.enabled = checked;
+                self.clipboard = watch_clipboard(&mut self.data);
+                let _ = clipboard::store_watching(self.data.clipboard.enabled);
+                Command::none()
+            }
+            Message::ClipboardTick => {
+                let query = match &mut self.clipboard {
+                    Some(watcher) => watcher.poll(time::Instant::now()),
+                    None => None,
+                };
+                match query {
+                    // what's copied while editing cards or text is meant for pasting there
+                    Some(query) if self.screen.follows_clipboard() => {
+                        let (loading, command) = Dict::loading(&mut self.data, query, false);
+                        self.navigation.go_to(&mut self.screen, loading);
+                        command
+                    }
+                    _ => Command::none(),
+                }
+            }
+            Message::RomajiDisplaySelected(romaji_display) => {
+                self.data.romaji_display = romaji_display;
+                Command::none()
+            }
+            Message::SearchTabSelected(search_tab) => {
+                self.data.search_tab = search_tab;
+                text_input::focus(text_input::Id::new(SEARCH_INPUT))
+            }
+            Message::HelpButtonPressed => {
+                match self.screen {
+                    Dict::Startup {} | Dict::Loading { .. } => {}
+                    Dict::Help {} => self.go_back(),
+                    _ => self.navigation.go_to(&mut self.screen, Dict::Help {}),
+                }
+                Command::none()
+            }
+            // Escape on the search screen asks whether to quit instead
+            Message::EscapeButtonPressed if !matches!(self.screen, Dict::Waiting { .. }) => {
+                self.go_back();
+                Command::none()
+            }
+            Message::BackButtonPressed => {
+                self.go_back();
+                Command::none()
+            }
+            Message::ForwardButtonPressed => {
+                if self.navigation.go_forward(&mut self.screen) {
+                    self.screen.refresh(&self.data);
+                }
+                Command::none()
+            }
+            Message::BatchWordFound(word, result) => {
+                self.data.batch_looking_up = false;
+                // the word may have been deleted from the list in the meantime
+                let index = self
+                    .data
+                    .batch
+                    .iter()
+                    .position(|entry| entry.word == word && entry.results.is_none());
+                if let Some(index) = index {
+                    // a failed lookup is flagged on the list
+                    let results = result
+                        .ok()
+                        .map(|response| search_results(&response, &self.data.example_sentences));
+                    self.data.batch[index].set_results(results);
+                    fill_batch_card(&mut self.data, index);
+                }
+                Dict::batch_command(&mut self.data)
+            }
+            Message::AnkiConnectExported(sent, Ok(added)) => {
+                flashcards::remove_exported(&mut self.data.pending_cards, &added);
+                let _ = flashcards::store_pending_cards(&self.data.pending_cards);
+                self.screen.update(
+                    &mut self.data,
+                    &mut self.navigation,
+                    Message::AnkiConnectExported(sent, Ok(added)),
+                )
+            }
+            Message::WindowResized(width, height) => {
+                self.window.width = width;
+                self.window.height = height;
+                Command::none()
+            }
+            Message::WindowMoved(x, y) => {
+                self.window.x = Some(x);
+                self.window.y = Some(y);
+                Command::none()
+            }
+            Message::CloseRequested | Message::QuitConfirmed => {
+                let _ = flashcards::store_pending_cards(&self.data.pending_cards);
+                let _ = history::store_history(&self.data.history);
+                let _ = favorites::store_favorites(&self.data.favorites);
+                let _ = config::store_window_geometry(&self.window);
+                window::close()
+            }
+            message => self
+                .screen
+                .update(&mut self.data, &mut self.navigation, message),
+        }
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        self.screen.view(&self.data)
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        // keys are resolved in `update`, which knows the current screen
+        let events = subscription::events_with(|event, status| match event {
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                modifiers,
+                key_code,
+            }) => Some(Message::KeyPressed(key_code, modifiers, status)),
+            // the back and forward buttons on the side of a mouse
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Other(button))) => match button
+            {
+                1 | 8 => Some(Message::BackButtonPressed),
+                2 | 9 => Some(Message::ForwardButtonPressed),
+                _ => None,
+            },
+            Event::Window(window::Event::Resized { width, height }) => {
+                Some(Message::WindowResized(width, height))
+            }
+            Event::Window(window::Event::Moved { x, y }) => Some(Message::WindowMoved(x, y)),
+            Event::Window(window::Event::CloseRequested) => Some(Message::CloseRequested),
+            _ => None,
+        });
+        let mut subscriptions = vec![events];
+        if let Dict::Details {
+            animate_strokes: true,
+            ..
+        } = self.screen
+        {
+            subscriptions
+                .push(time::every(STROKE_ANIMATION_INTERVAL).map(|_| Message::StrokeAnimationTick));
+        }
+        if self.clipboard.is_some() {
+            subscriptions
+                .push(time::every(clipboard::POLL_INTERVAL).map(|_| Message::ClipboardTick));
+        }
+        Subscription::batch(subscriptions)
+    }
+}
+
+impl App {
+    fn go_back(&mut self) {
+        // the search screen is home, there's nothing before it
+        if !self.navigation.can_go_back() && matches!(self.screen, Dict::Waiting { .. }) {
+            return;
+        }
+        self.navigation
+            .go_back(&mut self.screen, || Dict::waiting(&self.data));
+        self.screen.refresh(&self.data);
+    }
+
+    // Turns a key press into the message of the action it's bound to on the current
+    // screen. Keys a text box already handled only count when they're Ctrl/Alt shortcuts.
+    fn handle_hotkey(
+        &self,
+        key_code: keyboard::KeyCode,
+        modifiers: keyboard::Modifiers,
+        status: event::Status,
+    ) -> Option<Message> {
+        let screen = self.screen.screen()?;
+        let captured = status == event::Status::Captured;
+        if !captured && modifiers.is_empty() {
+            if let Some(n) = keymap::number_key(key_code) {
+                return Some(Message::NumberKeyPressed(n));
+            }
+        }
+
+        Some(
+            match self
+                .data
+                .keymap
+                .action(screen, key_code, modifiers, captured)?
+            {
+                Action::Confirm => Message::EnterButtonPressed,
+                Action::Back => Message::EscapeButtonPressed,
+                Action::Forward => Message::ForwardButtonPressed,
+                Action::Quit => Message::QButtonPressed,
+                Action::Next => Message::DownButtonPressed,
+                Action::Previous => Message::UpButtonPressed,
+                Action::ToggleTranslation => Message::TButtonPressed,
+                Action::ToggleFurigana => Message::FButtonPressed,
+                Action::SaveCard => Message::SaveCardButtonPressed,
+                Action::FocusSearch => Message::FocusSearchButtonPressed,
+                Action::Help => Message::HelpButtonPressed,
+            },
+        )
+    }
+}
+
+impl Page for Dict {
+    fn is_transient(&self) -> bool {
+        matches!(self, Dict::Startup {} | Dict::Loading { .. })
+    }
+}
+
 // Iterator yielding every line in a string. The line includes newline character(s).
 // https://stackoverflow.com/questions/40455997/iterate-over-lines-in-a-string-including-the-newline-characters
 #[derive(Debug, Clone)]
@@ -146,71 +733,63 @@ impl<'a> Iterator for LinesWithEndings<'a> {
     }
 }
 
-impl Application for Dict {
-    type Executor = iced::executor::Default;
-    type Message = Message;
-    type Theme = iced::Theme;
-    type Flags = ();
-
-    fn new(_flags: ()) -> (Self, Command<Message>) {
-        (
-            Dict::Startup {},
-            (Command::perform(
-                Dict::load_example_sentences(),
-                Message::FoundExampleSentences,
-            )),
-        )
-    }
-
+impl Dict {
     fn title(&self) -> String {
         String::from("Dict")
     }
 
-    fn update(&mut self, message: Message) -> Command<Message> {
+    fn update(
+        &mut self,
+        data: &mut Data,
+        navigation: &mut Navigation<Dict>,
+        message: Message,
+    ) -> Command<Message> {
         match self {
-            Dict::Startup {} => match message {
-                Message::FoundExampleSentences(result) => match result {
-                    Ok(sentences) => {
-                        let sentence_map: SentenceMap = Self::parse_example_sentences(sentences);
-
-                        println!("startup: finished loading sentences!");
-                        *self = Dict::Waiting {
-                            input_value: "".to_string(),
-                            example_sentences: sentence_map,
-                            show_modal: false,
-                        };
-                        Command::none()
-                    }
-                    Err(_error) => {
-                        // loading/parsing sentences somehow failed
-                        // TODO: do something
-                        Command::none()
-                    }
-                },
-                _ => {
-                    println!("startup: sentences not loaded yet!");
-                    Command::none()
-                }
-            },
+            Dict::Startup {} => {
+                println!("startup: sentences not loaded yet!");
+                Command::none()
+            }
             Dict::Waiting {
                 input_value,
-                example_sentences,
+                radical_picker,
+                history_cursor,
                 show_modal,
+                ..
             } => match message {
                 Message::InputChanged(value) => {
-                    *input_value = value;
+                    *input_value = romaji::to_kana(&value, data.input_mode, false);
+                    *history_cursor = None;
                     Command::none()
                 }
-                Message::SearchButtonPressed => {
+                Message::SearchButtonPressed | Message::EnterButtonPressed => {
+                    // a trailing n is only an ん once we know nothing follows,
+                    // searches picked from the history are kept as they were
+                    if history_cursor.is_none() {
+                        *input_value = romaji::to_kana(input_value, data.input_mode, true);
+                    }
                     let query = input_value.clone();
-                    let state_swap_example_sentences = std::mem::take(example_sentences);
-                    *self = Dict::Loading {
-                        example_sentences: state_swap_example_sentences,
-                    };
-                    println!("{}", query);
-                    Command::perform(Dict::search(query), Message::WordFound)
+                    if data.search_tab == SearchTab::Sentences {
+                        let matches = data.sentence_index.search(&query, MAX_SENTENCE_MATCHES);
+                        navigation.go_to(
+                            self,
+                            Dict::Sentences {
+                                query,
+                                matches,
+                                selected: 0,
+                            },
+                        );
+                        return Command::none();
+                    }
+                    let (loading, command) = Dict::loading(data, query, false);
+                    navigation.go_to(self, loading);
+                    command
+                }
+                Message::EscapeButtonPressed | Message::QButtonPressed => {
+                    self.update(data, navigation, Message::OpenModal)
+                }
+                Message::FocusSearchButtonPressed => {
+                    text_input::focus(text_input::Id::new(SEARCH_INPUT))
                 }
-                Message::EscapeButtonPressed => self.update(Message::OpenModal),
                 Message::OpenModal => {
                     *show_modal = true;
                     Command::none()
@@ -219,130 +798,954 @@ impl Application for Dict {
                     *show_modal = false;
                     Command::none()
                 }
-                Message::OkButtonPressed => {
-                    std::process::exit(0);
+                Message::PendingCardsButtonPressed => {
+                    navigation.go_to(
+                        self,
+                        Dict::Pending {
+                            status: "".to_string(),
+                            stroke_order_media: false,
+                        },
+                    );
+                    Command::none()
+                }
+                Message::UpButtonPressed | Message::DownButtonPressed
+                    if !data.history.is_empty() =>
+                {
+                    // up goes back in time, down towards the most recent search
+                    *history_cursor = match (message, *history_cursor) {
+                        (Message::UpButtonPressed, None) => Some(0),
+                        (Message::UpButtonPressed, Some(n)) => {
+                            Some((n + 1).min(data.history.len() - 1))
+                        }
+                        (_, Some(n)) if n > 0 => Some(n - 1),
+                        _ => None,
+                    };
+                    *input_value = match history_cursor {
+                        Some(n) => data.history[*n].query.clone(),
+                        None => "".to_string(),
+                    };
+                    Command::none()
+                }
+                Message::RadicalsButtonPressed => {
+                    *radical_picker = match radical_picker {
+                        Some(_) => None,
+                        None => Some(Vec::new()),
+                    };
+                    Command::none()
+                }
+                Message::RadicalPressed(radical) => {
+                    if let Some(selected) = radical_picker {
+                        match selected.iter().position(|r| *r == radical) {
+                            Some(index) => {
+                                selected.remove(index);
+                            }
+                            None => selected.push(radical),
+                        }
+                    }
+                    Command::none()
+                }
+                Message::ClearRadicalsButtonPressed => {
+                    if let Some(selected) = radical_picker {
+                        selected.clear();
+                    }
+                    Command::none()
+                }
+                Message::RadicalKanjiPressed(kanji) => {
+                    input_value.push(kanji);
+                    *history_cursor = None;
+                    text_input::focus(text_input::Id::new(SEARCH_INPUT))
+                }
+                Message::HistoryEntryPressed(query) => {
+                    // searched before, so it doesn't need converting to kana again
+                    let (loading, command) = Dict::loading(data, query, false);
+                    navigation.go_to(self, loading);
+                    command
+                }
+                Message::ClearHistoryButtonPressed => {
+                    data.history.clear();
+                    *history_cursor = None;
+                    let _ = history::store_history(&data.history);
+                    Command::none()
+                }
+                Message::FavoritesButtonPressed => {
+                    navigation.go_to(
+                        self,
+                        Dict::Favorites {
+                            filter: "".to_string(),
+                            sort: FavoritesSort::Newest,
+                            selected: HashSet::new(),
+                            status: "".to_string(),
+                        },
+                    );
+                    Command::none()
+                }
+                Message::GrammarButtonPressed => {
+                    navigation.go_to(self, Dict::grammar(data, 0));
+                    Command::none()
+                }
+                Message::AnalysisButtonPressed => {
+                    navigation.go_to(
+                        self,
+                        Dict::Analysis {
+                            text: "".to_string(),
+                            analysis: Analysis::default(),
+                            selected: HashSet::new(),
+                            show_known: false,
+                            status: "".to_string(),
+                        },
+                    );
+                    Command::none()
+                }
+                Message::StudyButtonPressed => {
+                    let queue = data
+                        .study_database
+                        .as_ref()
+                        .and_then(|database| srs::due_cards(database, srs::now()).ok())
+                        .unwrap_or_default();
+                    navigation.go_to(
+                        self,
+                        Dict::Study {
+                            queue,
+                            toggle_show_translation: false,
+                        },
+                    );
+                    Command::none()
                 }
                 _ => Command::none(),
             },
             Dict::Loading {
-                example_sentences, ..
+                query,
+                open_details,
+                search,
             } => match message {
-                Message::WordFound(Ok(jisho_result)) => {
-                    let mut search_results: Vec<SearchResult> = vec![];
-                    for i in &jisho_result.data {
-                        let japanese = &i.slug;
-                        let reading = &i.japanese[0].reading.clone().unwrap_or_default();
-                        let translation = &i.senses[0].english_definitions;
-                        search_results.push(SearchResult::new(
-                            japanese.clone(),
-                            reading.clone(),
-                            translation.clone(),
-                        ));
-                    }
-                    let state_swap_example_sentences = std::mem::take(example_sentences);
-                    *self = Dict::Loaded {
-                        search_results,
-                        example_sentences: state_swap_example_sentences,
+                // the request isn't aborted, answers nobody waits for anymore are dropped
+                Message::WordFound(found_search, _) if found_search != *search => Command::none(),
+                Message::WordFound(_, Ok(jisho_result)) => {
+                    history::record(
+                        &mut data.history,
+                        query,
+                        jisho_result.data.len(),
+                        srs::now(),
+                    );
+                    let _ = history::store_history(&data.history);
+
+                    let search_results = search_results(&jisho_result, &data.example_sentences);
+                    // the loading screen isn't kept in the navigation history
+                    let opened = search_results
+                        .iter()
+                        .find(|result| *open_details && result.japanese == *query)
+                        .cloned();
+                    let (screen, command) = match opened {
+                        Some(result) => Dict::details(
+                            data,
+                            result.japanese,
+                            result.reading,
+                            result.translations,
+                            search_results,
+                        ),
+                        None => (
+                            Dict::Loaded {
+                                search_results,
+                                selected: 0,
+                            },
+                            Command::none(),
+                        ),
                     };
-                    Command::none()
+                    *self = screen;
+                    command
                 }
-                Message::WordFound(Err(_error)) => {
-                    // Do something useful here
+                Message::WordFound(_, Err(_error)) => {
+                    // back to the search, with the query to try again
+                    let mut waiting = Dict::waiting(data);
+                    if let Dict::Waiting {
+                        input_value,
+                        status,
+                        ..
+                    } = &mut waiting
+                    {
+                        *input_value = query.clone();
+                        *status =
+                            format!("Could not look up {}, is the connection working?", query);
+                    }
+                    *self = waiting;
                     Command::none()
                 }
-                Message::EscapeButtonPressed => {
-                    std::process::exit(0);
-                }
                 _ => Command::none(),
             },
             Dict::Loaded {
-                example_sentences,
                 search_results,
-                ..
+                selected,
             } => match message {
-                Message::SearchAgainButtonPressed | Message::EscapeButtonPressed => {
-                    let state_swap_example_sentences = std::mem::take(example_sentences);
-                    *self = Dict::Waiting {
-                        input_value: "".to_string(),
-                        example_sentences: state_swap_example_sentences,
-                        show_modal: false,
-                    };
+                Message::SearchAgainButtonPressed | Message::FocusSearchButtonPressed => {
+                    navigation.go_to(self, Dict::waiting(data));
+                    text_input::focus(text_input::Id::new(SEARCH_INPUT))
+                }
+                Message::QButtonPressed => {
+                    navigation.go_to(self, Dict::waiting(data));
+                    self.update(data, navigation, Message::OpenModal)
+                }
+                Message::ResultsSortPressed(sort) => {
+                    data.results_order.sort_by(sort);
+                    *selected = 0;
+                    Command::none()
+                }
+                Message::CommonOnlyToggled(common_only) => {
+                    data.results_order.common_only = common_only;
+                    *selected = 0;
+                    Command::none()
+                }
+                Message::JlptFilterSelected(jlpt) => {
+                    data.results_order.jlpt = jlpt;
+                    *selected = 0;
                     Command::none()
                 }
+                Message::UpButtonPressed | Message::DownButtonPressed => {
+                    // `selected` is a position in the sorted and filtered list
+                    let shown = shown_results(search_results, data.results_order);
+                    if shown.is_empty() {
+                        return Command::none();
+                    }
+                    *selected = match message {
+                        Message::UpButtonPressed => selected.saturating_sub(1),
+                        _ => (*selected + 1).min(shown.len() - 1),
+                    };
+                    // keep the selected result in view
+                    let offset = *selected as f32 / (shown.len() - 1).max(1) as f32;
+                    scrollable::snap_to(scrollable::Id::new(SEARCH_RESULTS), offset)
+                }
+                Message::EnterButtonPressed => {
+                    let shown = shown_results(search_results, data.results_order);
+                    match shown.get(*selected).map(|&i| search_results[i].clone()) {
+                        Some(result) => self.update(
+                            data,
+                            navigation,
+                            Message::DetailsButtonPressed(
+                                result.japanese,
+                                result.reading,
+                                result.translations,
+                            ),
+                        ),
+                        None => Command::none(),
+                    }
+                }
                 Message::DetailsButtonPressed(word, reading, translations) => {
-                    *self = Dict::Details {
-                        word,
-                        reading,
-                        translations,
-                        toggle_show_translation: false,
-                        search_results: std::mem::take(search_results),
-                        example_sentences: std::mem::take(example_sentences),
-                        text_zoom_value: 18,
-                        show_modal: false,
+                    // keep the list selection on the word that was opened
+                    if let Some(position) = shown_results(search_results, data.results_order)
+                        .iter()
+                        .position(|&i| {
+                            let result = &search_results[i];
+                            result.japanese == word && result.reading == reading
+                        })
+                    {
+                        *selected = position;
+                    }
+                    let (details, command) =
+                        Dict::details(data, word, reading, translations, search_results.clone());
+                    navigation.go_to(self, details);
+                    command
+                }
+                Message::ToggleFavoriteButtonPressed(word, reading, translations) => {
+                    toggle_favorite(&mut data.favorites, word, reading, translations);
+                    Command::none()
+                }
+                _ => Command::none(),
+            },
+            Dict::Sentences {
+                matches, selected, ..
+            } => match message {
+                Message::SearchAgainButtonPressed | Message::FocusSearchButtonPressed => {
+                    navigation.go_to(self, Dict::waiting(data));
+                    text_input::focus(text_input::Id::new(SEARCH_INPUT))
+                }
+                Message::QButtonPressed => {
+                    navigation.go_to(self, Dict::waiting(data));
+                    self.update(data, navigation, Message::OpenModal)
+                }
+                Message::UpButtonPressed | Message::DownButtonPressed if !matches.is_empty() => {
+                    *selected = match message {
+                        Message::UpButtonPressed => selected.saturating_sub(1),
+                        _ => (*selected + 1).min(matches.len() - 1),
+                    };
+                    let offset = *selected as f32 / (matches.len() - 1).max(1) as f32;
+                    scrollable::snap_to(scrollable::Id::new(SENTENCE_MATCHES), offset)
+                }
+                _ => Command::none(),
+            },
+            Dict::Grammar {
+                selected,
+                matches,
+                selected_sentence,
+                status,
+            } => match message {
+                Message::FocusSearchButtonPressed => {
+                    navigation.go_to(self, Dict::waiting(data));
+                    text_input::focus(text_input::Id::new(SEARCH_INPUT))
+                }
+                Message::QButtonPressed => {
+                    navigation.go_to(self, Dict::waiting(data));
+                    self.update(data, navigation, Message::OpenModal)
+                }
+                Message::GrammarPatternSelected(index) => {
+                    *self = Dict::grammar(data, index);
+                    Command::none()
+                }
+                Message::UpButtonPressed | Message::DownButtonPressed
+                    if !data.grammar.is_empty() =>
+                {
+                    let index = match message {
+                        Message::UpButtonPressed => selected.saturating_sub(1),
+                        _ => (*selected + 1).min(data.grammar.len() - 1),
+                    };
+                    *self = Dict::grammar(data, index);
+                    let offset = index as f32 / (data.grammar.len() - 1).max(1) as f32;
+                    scrollable::snap_to(scrollable::Id::new(GRAMMAR_PATTERNS), offset)
+                }
+                Message::GrammarSentenceSelected(n) if n < matches.len() => {
+                    *selected_sentence = n;
+                    Command::none()
+                }
+                Message::NumberKeyPressed(n) if n >= 1 && n <= matches.len() => {
+                    *selected_sentence = n - 1;
+                    Command::none()
+                }
+                Message::GrammarCardButtonPressed(n) if n < matches.len() => {
+                    *selected_sentence = n;
+                    self.update(data, navigation, Message::SaveCardButtonPressed)
+                }
+                Message::SaveCardButtonPressed => {
+                    // no patterns at all when every one of them failed to compile
+                    let Some(pattern) = data.grammar.get(*selected).map(|p| &p.grammar) else {
+                        return Command::none();
+                    };
+                    if let Some(sentence) = matches
+                        .get(*selected_sentence)
+                        .and_then(|found| data.sentence_index.sentence(found.id))
+                    {
+                        save_flashcard(
+                            &mut data.pending_cards,
+                            data.study_database.as_ref(),
+                            grammar::grammar_flashcard(pattern, sentence),
+                        );
+                        let _ = flashcards::store_pending_cards(&data.pending_cards);
+                        *status = format!("Added a card for {}", pattern.name);
+                    }
+                    Command::none()
+                }
+                Message::GrammarCardsButtonPressed => {
+                    let Some(pattern) = data.grammar.get(*selected).map(|p| &p.grammar) else {
+                        return Command::none();
                     };
+                    let cards: Vec<Flashcard> = matches
+                        .iter()
+                        .filter_map(|found| data.sentence_index.sentence(found.id))
+                        .map(|sentence| grammar::grammar_flashcard(pattern, sentence))
+                        .collect();
+                    *status = format!("Added {} card(s) for {}", cards.len(), pattern.name);
+                    for card in cards {
+                        save_flashcard(&mut data.pending_cards, data.study_database.as_ref(), card);
+                    }
+                    let _ = flashcards::store_pending_cards(&data.pending_cards);
+                    Command::none()
+                }
+                _ => Command::none(),
+            },
+            Dict::Analysis {
+                text,
+                analysis,
+                selected,
+                show_known,
+                status,
+            } => match message {
+                Message::FocusSearchButtonPressed => {
+                    navigation.go_to(self, Dict::waiting(data));
+                    text_input::focus(text_input::Id::new(SEARCH_INPUT))
+                }
+                Message::AnalysisTextChanged(value) => {
+                    *text = value;
+                    Command::none()
+                }
+                Message::AnalyzeButtonPressed | Message::EnterButtonPressed => {
+                    *analysis = analyze_text(data, text);
+                    selected.clear();
+                    let vocabulary = analysis.vocabulary();
+                    let new = vocabulary
+                        .iter()
+                        .filter(|found| !data.known_words.contains(&found.word))
+                        .count();
+                    *status = format!("{} word(s), {} of them new", vocabulary.len(), new);
+                    Command::none()
+                }
+                Message::ShowKnownToggled(checked) => {
+                    *show_known = checked;
+                    Command::none()
+                }
+                Message::AnalysisWordSelected(word, checked) => {
+                    if checked {
+                        selected.insert(word);
+                    } else {
+                        selected.remove(&word);
+                    }
+                    Command::none()
+                }
+                Message::ToggleKnownButtonPressed(word) => {
+                    known_words::toggle(&mut data.known_words, &word);
+                    let _ = known_words::store_known_words(&data.known_words);
+                    selected.remove(&word);
                     Command::none()
                 }
+                Message::SentenceWordPressed(word, _) => {
+                    let (loading, command) = Dict::loading(data, word, true);
+                    navigation.go_to(self, loading);
+                    command
+                }
+                Message::AnalysisCardsButtonPressed => {
+                    let mut added = 0;
+                    for found in analysis.vocabulary() {
+                        if !selected.contains(&found.word)
+                            || data.batch.iter().any(|entry| entry.word == found.word)
+                        {
+                            continue;
+                        }
+                        let mut entry = BatchEntry::new(found.word);
+                        entry.context = Some(analysis.sentence_text(found.sentence));
+                        data.batch.push(entry);
+                        added += 1;
+                    }
+                    navigation.go_to(
+                        self,
+                        Dict::Batch {
+                            file: batch::BATCH_WORDS_FILE.to_string(),
+                            flagged_only: false,
+                            status: format!("Looking up {} word(s) from the text", added),
+                        },
+                    );
+                    Dict::batch_command(data)
+                }
                 _ => Command::none(),
             },
             Dict::Details {
-                example_sentences,
                 search_results,
                 word,
                 reading,
                 translations,
                 toggle_show_translation,
+                show_furigana,
                 text_zoom_value,
+                sentences,
+                selected_sentence,
                 show_modal,
+                stroke_orders,
+                stroke_step,
+                animate_strokes,
             } => match message {
-                Message::BackButtonPressed | Message::EscapeButtonPressed => {
-                    *self = Dict::Loaded {
-                        example_sentences: std::mem::take(example_sentences),
-                        search_results: std::mem::take(search_results),
+                Message::FocusSearchButtonPressed => {
+                    navigation.go_to(self, Dict::waiting(data));
+                    text_input::focus(text_input::Id::new(SEARCH_INPUT))
+                }
+                Message::QButtonPressed => {
+                    navigation.go_to(self, Dict::waiting(data));
+                    self.update(data, navigation, Message::OpenModal)
+                }
+                Message::NumberKeyPressed(n) => {
+                    if n >= 1 && n <= sentences.len() {
+                        *selected_sentence = Some(n - 1);
+                    }
+                    Command::none()
+                }
+                Message::SentenceOrderSelected(order) => {
+                    data.sentence_order = order;
+                    *sentences = shown_sentences(data, word);
+                    *selected_sentence = None;
+                    Command::none()
+                }
+                Message::ToggleKnownButtonPressed(toggled) => {
+                    known_words::toggle(&mut data.known_words, &toggled);
+                    let _ = known_words::store_known_words(&data.known_words);
+                    // the list of i+1 sentences changes with it
+                    *sentences = shown_sentences(data, word);
+                    *selected_sentence = None;
+                    Command::none()
+                }
+                Message::UpButtonPressed | Message::DownButtonPressed => {
+                    let sentence_count = sentences.len();
+                    if sentence_count > 0 {
+                        *selected_sentence = match (message, *selected_sentence) {
+                            (Message::UpButtonPressed, Some(n)) => Some(n.saturating_sub(1)),
+                            (Message::DownButtonPressed, Some(n)) => {
+                                Some((n + 1).min(sentence_count - 1))
+                            }
+                            _ => Some(0),
+                        };
+                    }
+                    Command::none()
+                }
+                Message::SaveCardButtonPressed if !*show_modal => {
+                    let sentence = match selected_sentence.and_then(|n| sentences.get(n)) {
+                        Some((sentence, ..)) => sentence.clone(),
+                        None => card_sentence(data, word),
                     };
+                    self.update(
+                        data,
+                        navigation,
+                        Message::CreateFlashcardButtonPressed(sentence),
+                    )
+                }
+                Message::EnterButtonPressed if *show_modal => {
+                    self.update(data, navigation, Message::OkButtonPressed)
+                }
+                Message::ToggleFavoriteButtonPressed(word, reading, translations) => {
+                    toggle_favorite(&mut data.favorites, word, reading, translations);
                     Command::none()
                 }
+                Message::KanjiPressed(kanji) => {
+                    // jisho's wildcard search for words containing the kanji
+                    let query = format!("*{}*", kanji);
+                    let (loading, command) = Dict::loading(data, query, false);
+                    navigation.go_to(self, loading);
+                    command
+                }
+                Message::SentenceWordPressed(headword, headword_reading) => {
+                    let known = search_results
+                        .iter()
+                        .find(|result| {
+                            result.japanese == headword
+                                && match &headword_reading {
+                                    Some(r) => katakana_to_hiragana(r) == result.reading,
+                                    None => true,
+                                }
+                        })
+                        .cloned();
+                    match known {
+                        Some(result) => {
+                            let (details, command) = Dict::details(
+                                data,
+                                result.japanese,
+                                result.reading,
+                                result.translations,
+                                search_results.clone(),
+                            );
+                            navigation.go_to(self, details);
+                            command
+                        }
+                        // not one of our search results, look it up first
+                        None => {
+                            let (loading, command) = Dict::loading(data, headword, true);
+                            navigation.go_to(self, loading);
+                            command
+                        }
+                    }
+                }
                 Message::CreateFlashcardButtonPressed(example_sentence) => {
-                    let card = BasicJapaneseFlashcard {
-                        vocab: word,
-                        vocab_kana: reading,
-                        vocab_translation: &translations.join(" / "),
-                        part_of_speech: "TODO",
-                        sentence: &example_sentence.japanese_text,
-                        sentence_translation: &example_sentence.english_text,
+                    let sentence = if data.card_furigana {
+                        let readings = dictionary_readings(search_results, word, reading);
+                        furigana::to_anki_furigana(&furigana::sentence_furigana(
+                            &example_sentence,
+                            &readings,
+                        ))
+                    } else {
+                        example_sentence.japanese_text
                     };
-                    let _ = store_word_to_csv(&card);
-                    self.update(Message::OpenModal)
+                    save_flashcard(
+                        &mut data.pending_cards,
+                        data.study_database.as_ref(),
+                        Flashcard {
+                            vocab: word.clone(),
+                            vocab_kana: reading.clone(),
+                            vocab_translation: translations.join(" / "),
+                            part_of_speech: "TODO".to_string(),
+                            sentence,
+                            sentence_translation: example_sentence.english_text,
+                            notes: "".to_string(),
+                        },
+                    );
+                    let _ = flashcards::store_pending_cards(&data.pending_cards);
+                    self.update(data, navigation, Message::OpenModal)
                 }
                 Message::ToggleShowTranslationButtonPressed | Message::TButtonPressed => {
                     // let current_state = *toggle_show_translation;
                     *toggle_show_translation = !(*toggle_show_translation);
                     Command::none()
                 }
+                Message::ToggleFuriganaButtonPressed | Message::FButtonPressed => {
+                    *show_furigana = !(*show_furigana);
+                    Command::none()
+                }
                 Message::TextSizeSliderChanged(new_size) => {
                     *text_zoom_value = new_size;
                     Command::none()
                 }
-                Message::OpenModal => {
-                    *show_modal = true;
+                Message::StrokeOrdersLoaded(loaded_word, loaded) if loaded_word == *word => {
+                    *stroke_orders = loaded;
                     Command::none()
                 }
-                Message::CancelButtonPressed | Message::CloseModal => {
-                    *show_modal = false;
+                Message::AnimateStrokesButtonPressed => {
+                    *animate_strokes = !*animate_strokes;
+                    if *animate_strokes && stroke_step.is_none() {
+                        *stroke_step = Some(0);
+                    }
                     Command::none()
                 }
-                Message::OkButtonPressed => self.update(Message::CloseModal),
+                Message::StrokeAnimationTick
+                | Message::PreviousStrokeButtonPressed
+                | Message::NextStrokeButtonPressed => {
+                    let total: usize = stroke_orders.iter().map(StrokeOrder::stroke_count).sum();
+                    let step = stroke_step.unwrap_or(total);
+                    let step = match message {
+                        Message::PreviousStrokeButtonPressed => step.saturating_sub(1),
+                        _ => (step + 1).min(total),
+                    };
+                    // back to the numbered diagrams once the last stroke is done
+                    *stroke_step = if step == total { None } else { Some(step) };
+                    *animate_strokes = *animate_strokes
+                        && matches!(message, Message::StrokeAnimationTick)
+                        && step < total;
+                    Command::none()
+                }
+                Message::OpenModal => {
+                    *show_modal = true;
+                    Command::none()
+                }
+                Message::CancelButtonPressed | Message::CloseModal => {
+                    *show_modal = false;
+                    Command::none()
+                }
+                Message::OkButtonPressed => self.update(data, navigation, Message::CloseModal),
                 Message::UndoButtonPressed => {
-                    let _ = delete_last_line_of_csv();
+                    if let Some(last) = data.pending_cards.len().checked_sub(1) {
+                        discard_flashcard(
+                            &mut data.pending_cards,
+                            data.study_database.as_ref(),
+                            last,
+                        );
+                        let _ = flashcards::store_pending_cards(&data.pending_cards);
+                    }
                     *show_modal = false;
                     Command::none()
                 }
                 _ => Command::none(),
             },
+            Dict::Pending {
+                status,
+                stroke_order_media,
+            } => {
+                let pending_cards = &mut data.pending_cards;
+                match message {
+                    Message::PendingCardEdited(index, field, value) => {
+                        if let Some(card) = pending_cards.get_mut(index) {
+                            card.set_field(field, value);
+                            let _ = flashcards::store_pending_cards(pending_cards);
+                        }
+                        Command::none()
+                    }
+                    Message::MovePendingCardUp(index) => {
+                        if index > 0 && index < pending_cards.len() {
+                            pending_cards.swap(index - 1, index);
+                            let _ = flashcards::store_pending_cards(pending_cards);
+                        }
+                        Command::none()
+                    }
+                    Message::MovePendingCardDown(index) => {
+                        if index + 1 < pending_cards.len() {
+                            pending_cards.swap(index, index + 1);
+                            let _ = flashcards::store_pending_cards(pending_cards);
+                        }
+                        Command::none()
+                    }
+                    Message::SwapPendingCardSentence(index) => {
+                        if let Some(card) = pending_cards.get_mut(index) {
+                            next_card_sentence(card, &data.example_sentences);
+                            let _ = flashcards::store_pending_cards(pending_cards);
+                        }
+                        Command::none()
+                    }
+                    Message::DeletePendingCard(index) => {
+                        if index < pending_cards.len() {
+                            discard_flashcard(pending_cards, data.study_database.as_ref(), index);
+                            let _ = flashcards::store_pending_cards(pending_cards);
+                        }
+                        Command::none()
+                    }
+                    Message::BatchButtonPressed => {
+                        navigation.go_to(
+                            self,
+                            Dict::Batch {
+                                file: batch::BATCH_WORDS_FILE.to_string(),
+                                flagged_only: false,
+                                status: "".to_string(),
+                            },
+                        );
+                        Command::none()
+                    }
+                    Message::ImportAnkiButtonPressed => {
+                        *status = match anki_import::import(&data.anki_import) {
+                            Ok(vocabulary) => {
+                                let added =
+                                    known_words::add_from_anki(&mut data.known_words, &vocabulary);
+                                let imported = vocabulary.len();
+                                data.anki_vocabulary.extend(vocabulary);
+                                let _ = anki_import::store_vocabulary(&data.anki_vocabulary);
+                                let _ = known_words::store_known_words(&data.known_words);
+                                format!(
+                                    "Imported {} word(s) from {}, {} of them newly known",
+                                    imported, data.anki_import.file, added
+                                )
+                            }
+                            Err(error) => {
+                                format!("Could not import {}: {}", data.anki_import.file, error)
+                            }
+                        };
+                        Command::none()
+                    }
+                    Message::ExportCsvButtonPressed => {
+                        *status = match flashcards::export_to_csv(pending_cards) {
+                            Ok(()) => {
+                                let exported = format!(
+                                    "Exported {} card(s) to {}",
+                                    pending_cards.len(),
+                                    flashcards::CSV_EXPORT_FILE
+                                );
+                                pending_cards.clear();
+                                let _ = flashcards::store_pending_cards(pending_cards);
+                                exported
+                            }
+                            Err(error) => format!("Export failed: {}", error),
+                        };
+                        Command::none()
+                    }
+                    Message::StrokeOrderMediaToggled(checked) => {
+                        *stroke_order_media = checked;
+                        Command::none()
+                    }
+                    Message::ExportApkgButtonPressed => {
+                        let (cards, media) = if *stroke_order_media {
+                            kanjivg::with_stroke_order_media(pending_cards)
+                        } else {
+                            (pending_cards.clone(), Vec::new())
+                        };
+                        *status =
+                            match apkg::write_apkg(flashcards::APKG_EXPORT_FILE, &cards, &media) {
+                                Ok(()) => {
+                                    let exported = format!(
+                                        "Exported {} card(s) to {}",
+                                        pending_cards.len(),
+                                        flashcards::APKG_EXPORT_FILE
+                                    );
+                                    pending_cards.clear();
+                                    let _ = flashcards::store_pending_cards(pending_cards);
+                                    exported
+                                }
+                                Err(error) => format!("Export failed: {}", error),
+                            };
+                        Command::none()
+                    }
+                    Message::ExportAnkiConnectButtonPressed => {
+                        *status = "Sending cards to Anki...".to_string();
+                        let sent = pending_cards.len();
+                        Command::perform(
+                            Dict::export_to_anki_connect(pending_cards.clone()),
+                            move |added| Message::AnkiConnectExported(sent, added),
+                        )
+                    }
+                    Message::AnkiConnectExported(sent, Ok(added)) => {
+                        *status = match sent - added.len() {
+                            0 => format!("Added {} card(s) to Anki", added.len()),
+                            refused => format!(
+                                "Added {} card(s) to Anki, {} refused (already in Anki?) \
+                                 are still pending",
+                                added.len(),
+                                refused
+                            ),
+                        };
+                        Command::none()
+                    }
+                    Message::AnkiConnectExported(_, Err(DictError::AnkiConnect(error))) => {
+                        *status = format!("AnkiConnect refused the cards: {}", error);
+                        Command::none()
+                    }
+                    Message::AnkiConnectExported(_, Err(_error)) => {
+                        *status = "Could not reach AnkiConnect, is Anki running?".to_string();
+                        Command::none()
+                    }
+                    _ => Command::none(),
+                }
+            }
+            Dict::Batch {
+                file,
+                flagged_only,
+                status,
+            } => match message {
+                Message::BatchFileChanged(value) => {
+                    *file = value;
+                    Command::none()
+                }
+                Message::LoadBatchButtonPressed | Message::EnterButtonPressed => {
+                    match std::fs::read_to_string(&*file) {
+                        Ok(text) => {
+                            data.batch = batch::read_word_list(&text)
+                                .into_iter()
+                                .map(BatchEntry::new)
+                                .collect();
+                            *status = format!("{} word(s) in {}", data.batch.len(), file);
+                            Dict::batch_command(data)
+                        }
+                        Err(error) => {
+                            *status = format!("Could not read {}: {}", file, error);
+                            Command::none()
+                        }
+                    }
+                }
+                Message::FlaggedOnlyToggled(checked) => {
+                    *flagged_only = checked;
+                    Command::none()
+                }
+                Message::NextBatchEntry(index) => {
+                    if let Some(chosen) = data.batch.get(index).and_then(BatchEntry::next_choice) {
+                        data.batch[index].chosen = chosen;
+                        fill_batch_card(data, index);
+                    }
+                    Command::none()
+                }
+                Message::BatchCardEdited(index, field, value) => {
+                    if let Some(card) = data.batch.get_mut(index).and_then(|e| e.card.as_mut()) {
+                        card.set_field(field, value);
+                    }
+                    Command::none()
+                }
+                Message::SwapBatchCardSentence(index) => {
+                    if let Some(card) = data.batch.get_mut(index).and_then(|e| e.card.as_mut()) {
+                        next_card_sentence(card, &data.example_sentences);
+                    }
+                    Command::none()
+                }
+                Message::DeleteBatchEntry(index) => {
+                    if index < data.batch.len() {
+                        data.batch.remove(index);
+                    }
+                    Command::none()
+                }
+                Message::AddBatchCardsButtonPressed => {
+                    // words without a card stay on the list
+                    let (done, left): (Vec<BatchEntry>, Vec<BatchEntry>) =
+                        data.batch.drain(..).partition(|entry| entry.card.is_some());
+                    data.batch = left;
+                    let added = done.len();
+                    for card in done.into_iter().filter_map(|entry| entry.card) {
+                        save_flashcard(&mut data.pending_cards, data.study_database.as_ref(), card);
+                    }
+                    let _ = flashcards::store_pending_cards(&data.pending_cards);
+                    *status = format!("Added {} card(s) to the pending cards", added);
+                    Command::none()
+                }
+                _ => Command::none(),
+            },
+            Dict::Favorites {
+                filter,
+                sort,
+                selected,
+                status,
+            } => match message {
+                Message::FavoritesFilterChanged(value) => {
+                    *filter = value;
+                    Command::none()
+                }
+                Message::FavoritesSortSelected(value) => {
+                    *sort = value;
+                    Command::none()
+                }
+                Message::FavoriteSelected(word, reading, is_selected) => {
+                    if is_selected {
+                        selected.insert((word, reading));
+                    } else {
+                        selected.remove(&(word, reading));
+                    }
+                    Command::none()
+                }
+                Message::SelectAllFavoritesButtonPressed => {
+                    *selected = favorites::filter_and_sort(&data.favorites, filter, *sort)
+                        .into_iter()
+                        .map(|i| {
+                            let favorite = &data.favorites[i];
+                            (favorite.word.clone(), favorite.reading.clone())
+                        })
+                        .collect();
+                    Command::none()
+                }
+                Message::ToggleFavoriteButtonPressed(word, reading, translations) => {
+                    selected.remove(&(word.clone(), reading.clone()));
+                    toggle_favorite(&mut data.favorites, word, reading, translations);
+                    Command::none()
+                }
+                Message::ExportFavoritesButtonPressed => {
+                    // favorites that were removed since they were selected are skipped
+                    let chosen: Vec<Favorite> = data
+                        .favorites
+                        .iter()
+                        .filter(|f| selected.contains(&(f.word.clone(), f.reading.clone())))
+                        .cloned()
+                        .collect();
+                    selected.clear();
+                    for favorite in &chosen {
+                        let sentence = card_sentence(data, &favorite.word);
+                        let card = new_flashcard(
+                            &favorite.word,
+                            &favorite.reading,
+                            &favorite.translations,
+                            sentence,
+                            data.card_furigana,
+                        );
+                        save_flashcard(&mut data.pending_cards, data.study_database.as_ref(), card);
+                    }
+                    let _ = flashcards::store_pending_cards(&data.pending_cards);
+                    *status = format!("Added {} card(s) to the pending cards", chosen.len());
+                    Command::none()
+                }
+                _ => Command::none(),
+            },
+            Dict::Study {
+                queue,
+                toggle_show_translation,
+            } => match message {
+                Message::ToggleShowTranslationButtonPressed | Message::TButtonPressed => {
+                    *toggle_show_translation = !(*toggle_show_translation);
+                    Command::none()
+                }
+                Message::EnterButtonPressed => {
+                    *toggle_show_translation = true;
+                    Command::none()
+                }
+                Message::NumberKeyPressed(n) if *toggle_show_translation => {
+                    match Grade::ALL.get(n.wrapping_sub(1)) {
+                        Some(grade) => {
+                            self.update(data, navigation, Message::GradeButtonPressed(*grade))
+                        }
+                        None => Command::none(),
+                    }
+                }
+                Message::GradeButtonPressed(grade) => {
+                    if queue.is_empty() {
+                        return Command::none();
+                    }
+                    let card = queue.remove(0);
+                    let reviewed = data.study_database.as_ref().and_then(|database| {
+                        srs::review(database, &card.word, grade, srs::now()).ok()
+                    });
+                    // forgotten cards come back at the end of this session
+                    if let Some(Some(reviewed)) = reviewed {
+                        if grade == Grade::Again {
+                            queue.push(reviewed);
+                        }
+                    }
+                    *toggle_show_translation = false;
+                    Command::none()
+                }
+                _ => Command::none(),
+            },
+            Dict::Help {} => Command::none(),
         }
     }
 
-    fn view(&self) -> Element<Message> {
-        return match self {
+    fn view<'a>(&'a self, data: &'a Data) -> Element<'a, Message> {
+        match self {
             Dict::Startup {} => {
                 let column = Column::new()
                     .width(Length::Shrink)
@@ -353,12 +1756,16 @@ impl Application for Dict {
                     .padding(30)
                     .into()
             }
-            Dict::Loading {
-                example_sentences: _,
-            } => {
+            Dict::Loading { .. } => {
                 let column = Column::new()
                     .width(Length::Shrink)
-                    .push(Text::new("Loading...").size(40));
+                    .spacing(20)
+                    .push(Text::new("Loading...").size(40))
+                    .push(
+                        Button::new(Text::new("Stop waiting").size(20))
+                            .padding(10)
+                            .on_press(Message::BackButtonPressed),
+                    );
                 Container::new(column)
                     .width(Length::Fill)
                     .height(Length::Fill)
@@ -367,10 +1774,14 @@ impl Application for Dict {
             }
             Dict::Waiting {
                 input_value,
-                example_sentences: _,
+                radical_picker,
+                due_count,
+                history_cursor,
                 show_modal,
+                status,
             } => {
-                let column = Column::new()
+                let (pending_cards, history) = (&data.pending_cards, &data.history);
+                let mut column = Column::new()
                     .width(Length::Fill)
                     .height(Length::Fill)
                     .align_items(Alignment::Start)
@@ -378,22 +1789,154 @@ impl Application for Dict {
                     .spacing(10)
                     .push(Text::new("Search the dictionary:").size(40))
                     .push(
-                        Row::new().spacing(10).push(
-                            TextInput::new(
-                                // input,
-                                "Type something...",
-                                input_value,
-                                Message::InputChanged,
+                        Row::new()
+                            .spacing(5)
+                            .align_items(Alignment::Center)
+                            .push(search_tab_button(
+                                "Words",
+                                SearchTab::Words,
+                                data.search_tab,
+                            ))
+                            .push(search_tab_button(
+                                "Sentences",
+                                SearchTab::Sentences,
+                                data.search_tab,
+                            ))
+                            .push(Space::new(Length::Units(20), Length::Units(1)))
+                            .push(Checkbox::new(
+                                data.clipboard.enabled,
+                                "Look up copied text",
+                                Message::WatchClipboardToggled,
+                            )),
+                    )
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(
+                                TextInput::new(
+                                    // input,
+                                    "Type something...",
+                                    input_value,
+                                    Message::InputChanged,
+                                )
+                                .id(text_input::Id::new(SEARCH_INPUT))
+                                .on_submit(Message::SearchButtonPressed)
+                                .padding(10)
+                                .size(25),
                             )
-                            .padding(10)
-                            .size(25),
-                        ),
+                            .push(
+                                PickList::new(
+                                    &InputMode::ALL[..],
+                                    Some(data.input_mode),
+                                    Message::InputModeSelected,
+                                )
+                                .text_size(20),
+                            ),
                     )
                     .push(
-                        Button::new(Text::new("Search").size(20))
-                            .padding(10)
-                            .on_press(Message::SearchButtonPressed), // .style(style::Button::Primary),
+                        Row::new()
+                            .spacing(10)
+                            .push(
+                                Button::new(Text::new("Search").size(20))
+                                    .padding(10)
+                                    .on_press(Message::SearchButtonPressed), // .style(style::Button::Primary),
+                            )
+                            .push(
+                                Button::new(
+                                    Text::new(format!("Pending cards ({})", pending_cards.len()))
+                                        .size(20),
+                                )
+                                .padding(10)
+                                .on_press(Message::PendingCardsButtonPressed),
+                            )
+                            .push(
+                                Button::new(
+                                    Text::new(format!("Study ({} due)", due_count)).size(20),
+                                )
+                                .padding(10)
+                                .on_press(Message::StudyButtonPressed),
+                            )
+                            .push(
+                                Button::new(Text::new("Favorites").size(20))
+                                    .padding(10)
+                                    .on_press(Message::FavoritesButtonPressed),
+                            )
+                            .push(
+                                Button::new(Text::new("Grammar").size(20))
+                                    .padding(10)
+                                    .on_press(Message::GrammarButtonPressed),
+                            )
+                            .push(
+                                Button::new(Text::new("Analyze text").size(20))
+                                    .padding(10)
+                                    .on_press(Message::AnalysisButtonPressed),
+                            )
+                            .push(if data.radicals.is_empty() {
+                                // no RADKFILE, nothing to pick from
+                                Button::new(Text::new("Radicals").size(20)).padding(10)
+                            } else {
+                                Button::new(Text::new("Radicals").size(20))
+                                    .padding(10)
+                                    .on_press(Message::RadicalsButtonPressed)
+                            }),
+                    );
+
+                if let Some(error) = &data.clipboard_error {
+                    column = column
+                        .push(Text::new(format!("Can't look up copied text: {}", error)).size(16));
+                }
+                if data.radicals_missing {
+                    column = column.push(
+                        Text::new(format!(
+                            "Radical picker unavailable: {} could not be read",
+                            radicals::RADKFILE
+                        ))
+                        .size(16),
                     );
+                }
+                if !status.is_empty() {
+                    column = column.push(Text::new(status.clone()).size(20));
+                }
+
+                if let Some(selected) = radical_picker {
+                    column = column.push(radical_picker_view(
+                        &data.radicals,
+                        &data.kanjidic,
+                        selected,
+                    ));
+                }
+
+                if !history.is_empty() {
+                    column = column.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(Text::new("Recent searches:").size(25))
+                            .push(
+                                Button::new(Text::new("Clear").size(16))
+                                    .padding(5)
+                                    .on_press(Message::ClearHistoryButtonPressed),
+                            ),
+                    );
+                    let now = srs::now();
+                    let mut entries = Column::new().spacing(5);
+                    for (n, entry) in history.iter().enumerate() {
+                        let label = format!(
+                            "{}{}  ({} results, {})",
+                            if *history_cursor == Some(n) { "> " } else { "" },
+                            entry.query,
+                            entry.result_count,
+                            history::time_ago(entry.timestamp, now)
+                        );
+                        entries = entries.push(
+                            Button::new(Text::new(label).size(18))
+                                .padding(5)
+                                .on_press(Message::HistoryEntryPressed(entry.query.clone())),
+                        );
+                    }
+                    column = column.push(scrollable(entries));
+                }
 
                 let modal = Modal::new(*show_modal, column, || {
                     Card::new(
@@ -411,7 +1954,7 @@ impl Application for Dict {
                                 )
                                 // .style(style::Button::Primary)
                                 .width(Length::Fill)
-                                .on_press(Message::OkButtonPressed),
+                                .on_press(Message::QuitConfirmed),
                             )
                             .push(
                                 Button::new(
@@ -438,26 +1981,62 @@ impl Application for Dict {
 
             Dict::Loaded {
                 search_results,
-                example_sentences: _,
+                selected,
             } => {
+                let favorites = &data.favorites;
+                let order = data.results_order;
+                let shown = shown_results(search_results, order);
                 let mut content = Column::new()
                     .spacing(5)
                     .align_items(Alignment::Start)
                     .height(Length::Fill)
                     .push(
-                        Button::new(Text::new("Search Again").size(25))
-                            .padding(10)
-                            .on_press(Message::SearchAgainButtonPressed), // .style(style::Button::Secondary),
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(
+                                Button::new(Text::new("Search Again").size(25))
+                                    .padding(10)
+                                    .on_press(Message::SearchAgainButtonPressed), // .style(style::Button::Secondary),
+                            )
+                            .push(
+                                PickList::new(
+                                    &RomajiDisplay::ALL[..],
+                                    Some(data.romaji_display),
+                                    Message::RomajiDisplaySelected,
+                                )
+                                .text_size(20),
+                            )
+                            .push(Checkbox::new(
+                                order.common_only,
+                                "Common words only",
+                                Message::CommonOnlyToggled,
+                            ))
+                            .push(
+                                PickList::new(
+                                    &JlptFilter::ALL[..],
+                                    Some(order.jlpt),
+                                    Message::JlptFilterSelected,
+                                )
+                                .text_size(20),
+                            ),
                     )
                     .push(
-                        Text::new(format!("{} results:", &search_results.len()))
-                            .size(30)
-                            .width(Length::Fill),
+                        Text::new(if shown.len() == search_results.len() {
+                            format!("{} results:", search_results.len())
+                        } else {
+                            format!("{} of {} results:", shown.len(), search_results.len())
+                        })
+                        .size(30)
+                        .width(Length::Fill),
                     );
 
                 let row = Row::new()
                     .spacing(10)
-                    .push(Space::new(Length::FillPortion(1), Length::Units(1)))
+                    .push(
+                        sort_header("Jisho order", ResultsSort::Relevance, order)
+                            .width(Length::FillPortion(1)),
+                    )
                     .push(
                         Text::new("Word")
                             .size(30)
@@ -475,10 +2054,18 @@ impl Application for Dict {
                             .size(30)
                             .width(Length::Fill)
                             .style(Color::new(0.67, 0.61, 0.60, 1.0)),
+                    )
+                    .push(
+                        sort_header("Common", ResultsSort::Common, order).width(Length::Units(100)),
+                    )
+                    .push(sort_header("JLPT", ResultsSort::Jlpt, order).width(Length::Units(70)))
+                    .push(
+                        sort_header("Sentences", ResultsSort::Frequency, order)
+                            .width(Length::Units(110)),
                     );
                 content = content.push(row);
 
-                for i in search_results.iter() {
+                for (n, i) in shown.iter().map(|&i| &search_results[i]).enumerate() {
                     let button = |label: String, message: Message| {
                         Button::new(
                             Text::new(label)
@@ -492,8 +2079,27 @@ impl Application for Dict {
                         .padding(4)
                     };
 
+                    let star = if favorites::is_favorite(favorites, &i.japanese, &i.reading) {
+                        "★"
+                    } else {
+                        "☆"
+                    };
+                    let color = if n == *selected {
+                        Color::new(0.85, 0.45, 0.2, 1.0)
+                    } else {
+                        Color::BLACK
+                    };
                     let row = Row::new()
                         .spacing(10)
+                        .push(
+                            Button::new(Text::new(star).size(16))
+                                .on_press(Message::ToggleFavoriteButtonPressed(
+                                    i.japanese.clone(),
+                                    i.reading.clone(),
+                                    i.translations.clone(),
+                                ))
+                                .padding(4),
+                        )
                         .push(button(
                             "details".to_string(),
                             Message::DetailsButtonPressed(
@@ -502,18 +2108,48 @@ impl Application for Dict {
                                 i.translations.clone(),
                             ),
                         ))
-                        .push(Text::new(i.japanese.clone()).size(30).width(Length::Fill))
-                        .push(Text::new(i.reading.clone()).size(30).width(Length::Fill))
+                        .push(
+                            Text::new(i.japanese.clone())
+                                .size(30)
+                                .width(Length::Fill)
+                                .style(color),
+                        )
+                        .push(
+                            Text::new(with_romaji(&i.reading, data.romaji_display))
+                                .size(30)
+                                .width(Length::Fill)
+                                .style(color),
+                        )
                         .push(
                             Text::new(i.translations.clone().join(" / "))
                                 .size(30)
                                 .width(Length::Fill)
-                                .horizontal_alignment(Horizontal::Left),
+                                .horizontal_alignment(Horizontal::Left)
+                                .style(color),
+                        )
+                        .push(
+                            Text::new(if i.ranking.is_common { "common" } else { "" })
+                                .size(20)
+                                .width(Length::Units(100)),
+                        )
+                        .push(
+                            Text::new(match i.ranking.jlpt {
+                                Some(level) => format!("N{}", level),
+                                None => "".to_string(),
+                            })
+                            .size(20)
+                            .width(Length::Units(70)),
+                        )
+                        .push(
+                            Text::new(i.ranking.frequency.to_string())
+                                .size(20)
+                                .width(Length::Units(110)),
                         );
                     content = content.push(row);
                 }
 
-                let scrollable = scrollable(Container::new(content).width(Length::Fill).center_x());
+                let scrollable = scrollable(Container::new(content).width(Length::Fill).center_x())
+                    .id(scrollable::Id::new(SEARCH_RESULTS));
 
                 Container::new(scrollable)
                     .width(Length::Fill)
@@ -521,70 +2157,454 @@ impl Application for Dict {
                     .padding(30)
                     .into()
             }
-            Dict::Details {
-                word,
-                reading,
-                translations,
-                example_sentences,
-                toggle_show_translation,
-                text_zoom_value,
-                search_results: _,
-                show_modal,
+            Dict::Sentences {
+                query,
+                matches,
+                selected,
             } => {
-                let sentences = match example_sentences.get(word) {
-                    Some(sentences) => sentences.to_owned(),
-                    None => Vec::new(),
-                };
-
-                let maybe_shortest_sentence: Option<_> = sentences
-                    .iter()
-                    .min_by(|s1, s2| (s1.english_text.len().cmp(&s2.english_text.len())));
-
-                let shortest_sentence: ExampleSentence =
-                    if let Some(sentence) = maybe_shortest_sentence {
-                        sentence.clone()
-                    } else {
-                        ExampleSentence::default()
-                    };
-
-                let mut column = Column::new()
-                    .align_items(Alignment::Start)
-                    .height(Length::Fill)
+                let mut content = Column::new()
                     .spacing(10)
+                    .align_items(Alignment::Start)
                     .push(
                         Row::new()
                             .spacing(10)
                             .push(
                                 Button::new(Text::new("Back").size(20))
                                     .padding(10)
-                                    .on_press(Message::BackButtonPressed), // .style(style::Button::Secondary),
-                            )
-                            .push(
-                                Button::new(
-                                    Text::new("Save to Anki flash card")
-                                        .width(Length::Fill)
-                                        .horizontal_alignment(Horizontal::Center)
-                                        .size(16),
-                                )
-                                .on_press(Message::CreateFlashcardButtonPressed(shortest_sentence))
-                                // .style(style::Button::Primary)
-                                .padding(10),
+                                    .on_press(Message::BackButtonPressed),
                             )
                             .push(
-                                Button::new(
-                                    Text::new(if *toggle_show_translation {
-                                        "Hide translation"
-                                    } else {
-                                        "Show translation"
-                                    })
-                                    .width(Length::Fill)
-                                    .horizontal_alignment(Horizontal::Center)
+                                Button::new(Text::new("Search Again").size(20))
+                                    .padding(10)
+                                    .on_press(Message::SearchAgainButtonPressed),
+                            ),
+                    )
+                    .push(
+                        Text::new(format!(
+                            "{}{} sentence(s) for \"{}\":",
+                            if matches.len() == MAX_SENTENCE_MATCHES {
+                                "The best "
+                            } else {
+                                ""
+                            },
+                            matches.len(),
+                            query
+                        ))
+                        .size(30),
+                    );
+                for (n, found) in matches.iter().enumerate() {
+                    let sentence = match data.sentence_index.sentence(found.id) {
+                        Some(sentence) => sentence,
+                        None => continue,
+                    };
+                    // the highlights are in the text that was searched
+                    let (japanese, english) = if found.english {
+                        (
+                            Text::new(sentence.japanese_text.clone()).size(25).into(),
+                            highlighted_text(&sentence.english_text, &found.highlights, 20),
+                        )
+                    } else {
+                        (
+                            highlighted_text(&sentence.japanese_text, &found.highlights, 25),
+                            Text::new(sentence.english_text.clone()).size(20).into(),
+                        )
+                    };
+                    let marker = if n == *selected { ">" } else { "" };
+                    content = content.push(
+                        Row::new()
+                            .spacing(10)
+                            .push(Text::new(marker).size(25).width(Length::Units(20)))
+                            .push(Column::new().spacing(2).push(japanese).push(english)),
+                    );
+                }
+
+                Container::new(scrollable(content).id(scrollable::Id::new(SENTENCE_MATCHES)))
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(30)
+                    .into()
+            }
+            Dict::Grammar {
+                selected,
+                matches,
+                selected_sentence,
+                status,
+            } => {
+                let mut patterns = Column::new().spacing(2);
+                for (n, pattern) in data.grammar.iter().enumerate() {
+                    let style = if n == *selected {
+                        theme::Button::Primary
+                    } else {
+                        theme::Button::Text
+                    };
+                    let level = match pattern.grammar.jlpt {
+                        Some(level) => format!("N{}", level),
+                        None => "".to_string(),
+                    };
+                    patterns = patterns.push(
+                        Button::new(
+                            Row::new()
+                                .spacing(10)
+                                .push(Text::new(level).size(16).width(Length::Units(30)))
+                                .push(Text::new(pattern.grammar.name.clone()).size(20)),
+                        )
+                        .width(Length::Fill)
+                        .style(style)
+                        .on_press(Message::GrammarPatternSelected(n)),
+                    );
+                }
+
+                let mut details = Column::new().spacing(10).push(
+                    Row::new()
+                        .spacing(10)
+                        .push(
+                            Button::new(Text::new("Back").size(20))
+                                .padding(10)
+                                .on_press(Message::BackButtonPressed),
+                        )
+                        .push(
+                            Button::new(
+                                Text::new(format!("Add {} card(s)", matches.len())).size(20),
+                            )
+                            .padding(10)
+                            .on_press(Message::GrammarCardsButtonPressed),
+                        )
+                        .push(Text::new(status.clone()).size(20)),
+                );
+                if let Some(pattern) = data.grammar.get(*selected) {
+                    let grammar = &pattern.grammar;
+                    details = details
+                        .push(Text::new(grammar.name.clone()).size(40))
+                        .push(
+                            Text::new(match grammar.jlpt {
+                                Some(level) => format!("JLPT N{}", level),
+                                None => "No JLPT level".to_string(),
+                            })
+                            .size(18),
+                        )
+                        .push(Text::new(grammar.explanation.clone()).size(22))
+                        .push(
+                            Text::new(format!(
+                                "{}{} sentence(s):",
+                                if matches.len() == MAX_GRAMMAR_SENTENCES {
+                                    "The shortest "
+                                } else {
+                                    ""
+                                },
+                                matches.len()
+                            ))
+                            .size(25),
+                        );
+                }
+                let mut sentences = Column::new().spacing(10);
+                for (n, found) in matches.iter().enumerate() {
+                    let sentence = match data.sentence_index.sentence(found.id) {
+                        Some(sentence) => sentence,
+                        None => continue,
+                    };
+                    let marker = if n == *selected_sentence { ">" } else { "" };
+                    sentences = sentences.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(Text::new(marker).size(25).width(Length::Units(20)))
+                            .push(
+                                Button::new(
+                                    Column::new()
+                                        .spacing(2)
+                                        .push(highlighted_text(
+                                            &sentence.japanese_text,
+                                            &found.highlights,
+                                            25,
+                                        ))
+                                        .push(Text::new(sentence.english_text.clone()).size(20)),
+                                )
+                                .width(Length::Fill)
+                                .style(theme::Button::Text)
+                                .on_press(Message::GrammarSentenceSelected(n)),
+                            )
+                            .push(
+                                Button::new(Text::new("Card").size(18))
+                                    .padding(8)
+                                    .on_press(Message::GrammarCardButtonPressed(n)),
+                            ),
+                    );
+                }
+
+                Container::new(
+                    Row::new()
+                        .spacing(30)
+                        .push(
+                            Container::new(
+                                scrollable(patterns)
+                                    .id(scrollable::Id::new(GRAMMAR_PATTERNS))
+                                    .height(Length::Fill),
+                            )
+                            .width(Length::Units(320)),
+                        )
+                        .push(
+                            details
+                                .push(scrollable(sentences).height(Length::Fill))
+                                .width(Length::FillPortion(3)),
+                        ),
+                )
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .padding(30)
+                .into()
+            }
+            Dict::Analysis {
+                text,
+                analysis,
+                selected,
+                show_known,
+                status,
+            } => {
+                let new_color = Color::new(0.85, 0.45, 0.2, 1.0);
+                let mut column = Column::new()
+                    .align_items(Alignment::Start)
+                    .spacing(10)
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(
+                                Button::new(Text::new("Back").size(20))
+                                    .padding(10)
+                                    .on_press(Message::BackButtonPressed),
+                            )
+                            .push(
+                                TextInput::new(
+                                    "Paste Japanese text...",
+                                    text,
+                                    Message::AnalysisTextChanged,
+                                )
+                                .on_submit(Message::AnalyzeButtonPressed)
+                                .padding(10)
+                                .size(20),
+                            )
+                            .push(
+                                Button::new(Text::new("Analyze").size(20))
+                                    .padding(10)
+                                    .on_press(Message::AnalyzeButtonPressed),
+                            ),
+                    )
+                    .push(Text::new(status.clone()).size(20));
+
+                // the text itself, every word can be clicked, new ones stand out
+                for tokens in &analysis.sentences {
+                    let row = tokens
+                        .iter()
+                        .fold(Row::new(), |row, token| match &token.word {
+                            Some(word) => {
+                                let mut label = Text::new(token.surface.clone()).size(24);
+                                if !data.known_words.contains(word) {
+                                    label = label.style(new_color);
+                                }
+                                row.push(
+                                    Button::new(label)
+                                        .padding(0)
+                                        .style(theme::Button::Text)
+                                        .on_press(Message::SentenceWordPressed(word.clone(), None)),
+                                )
+                            }
+                            None => row.push(Text::new(token.surface.clone()).size(24)),
+                        });
+                    column = column.push(row);
+                }
+
+                let vocabulary = analysis.vocabulary();
+                if !vocabulary.is_empty() {
+                    column = column.push(
+                        Row::new()
+                            .spacing(20)
+                            .align_items(Alignment::Center)
+                            .push(Text::new(format!("{} word(s):", vocabulary.len())).size(30))
+                            .push(Checkbox::new(
+                                *show_known,
+                                "Show known words",
+                                Message::ShowKnownToggled,
+                            ))
+                            .push(
+                                Button::new(
+                                    Text::new(format!("Make {} card(s)", selected.len())).size(16),
+                                )
+                                .padding(10)
+                                .on_press(Message::AnalysisCardsButtonPressed),
+                            ),
+                    );
+                }
+                for found in vocabulary {
+                    let known = data.known_words.contains(&found.word);
+                    if known && !*show_known {
+                        continue;
+                    }
+                    let word = found.word.clone();
+                    // how it was written, if not in its dictionary form
+                    let forms: Vec<&str> = found
+                        .forms
+                        .iter()
+                        .map(String::as_str)
+                        .filter(|form| *form != found.word)
+                        .collect();
+                    column = column.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(Checkbox::new(
+                                selected.contains(&found.word),
+                                found.word.clone(),
+                                move |checked| Message::AnalysisWordSelected(word.clone(), checked),
+                            ))
+                            .push(Text::new(forms.join(", ")).size(16))
+                            .push(Text::new(format!("×{}", found.count)).size(16))
+                            .push(
+                                Text::new(vocabulary_status(data, &found.word))
+                                    .size(16)
+                                    .style(if known { Color::BLACK } else { new_color }),
+                            )
+                            .push(Space::new(Length::Fill, Length::Units(1)))
+                            .push(
+                                Button::new(Text::new("Look up").size(16))
+                                    .padding(4)
+                                    .on_press(Message::SentenceWordPressed(
+                                        found.word.clone(),
+                                        None,
+                                    )),
+                            )
+                            .push(
+                                Button::new(
+                                    Text::new(if known { "✓ Known" } else { "Mark as known" })
+                                        .size(16),
+                                )
+                                .padding(4)
+                                .on_press(Message::ToggleKnownButtonPressed(found.word)),
+                            ),
+                    );
+                }
+
+                let scrollable = scrollable(Container::new(column).width(Length::Fill).center_x());
+
+                Container::new(scrollable)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(30)
+                    .into()
+            }
+            Dict::Details {
+                word,
+                reading,
+                translations,
+                toggle_show_translation,
+                show_furigana,
+                text_zoom_value,
+                search_results,
+                sentences,
+                selected_sentence,
+                show_modal,
+                stroke_orders,
+                stroke_step,
+                animate_strokes,
+            } => {
+                let (pending_cards, favorites) = (&data.pending_cards, &data.favorites);
+                let readings = dictionary_readings(search_results, word, reading);
+                let shown = sentences;
+
+                let card_sentence: ExampleSentence =
+                    match selected_sentence.and_then(|n| shown.get(n)) {
+                        Some((sentence, ..)) => sentence.clone(),
+                        None => card_sentence(data, word),
+                    };
+                let star = if favorites::is_favorite(favorites, word, reading) {
+                    "★ Favorite"
+                } else {
+                    "☆ Favorite"
+                };
+
+                let mut column = Column::new()
+                    .align_items(Alignment::Start)
+                    .height(Length::Fill)
+                    .spacing(10)
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .push(
+                                Button::new(Text::new("Back").size(20))
+                                    .padding(10)
+                                    .on_press(Message::BackButtonPressed), // .style(style::Button::Secondary),
+                            )
+                            .push(
+                                Button::new(
+                                    Text::new("Save to Anki flash card")
+                                        .width(Length::Fill)
+                                        .horizontal_alignment(Horizontal::Center)
+                                        .size(16),
+                                )
+                                .on_press(Message::CreateFlashcardButtonPressed(card_sentence))
+                                // .style(style::Button::Primary)
+                                .padding(10),
+                            )
+                            .push(
+                                Button::new(Text::new(star).size(16))
+                                    .on_press(Message::ToggleFavoriteButtonPressed(
+                                        word.clone(),
+                                        reading.clone(),
+                                        translations.clone(),
+                                    ))
+                                    .padding(10),
+                            )
+                            .push(
+                                Button::new(
+                                    Text::new(if data.known_words.contains(word) {
+                                        "✓ Known"
+                                    } else {
+                                        "Mark as known"
+                                    })
+                                    .size(16),
+                                )
+                                .on_press(Message::ToggleKnownButtonPressed(word.clone()))
+                                .padding(10),
+                            )
+                            .push(
+                                Text::new(match data.anki_vocabulary.get(word) {
+                                    Some(anki_word) if anki_word.interval > 0 => format!(
+                                        "In Anki: {}, every {} day(s)",
+                                        anki_word.state, anki_word.interval
+                                    ),
+                                    Some(anki_word) => format!("In Anki: {}", anki_word.state),
+                                    None => "".to_string(),
+                                })
+                                .size(16),
+                            )
+                            .push(
+                                Button::new(
+                                    Text::new(if *toggle_show_translation {
+                                        "Hide translation"
+                                    } else {
+                                        "Show translation"
+                                    })
+                                    .width(Length::Fill)
+                                    .horizontal_alignment(Horizontal::Center)
                                     .size(16),
                                 )
                                 .on_press(Message::ToggleShowTranslationButtonPressed)
                                 // .style(if *toggle_show_translation  { style::Button::Secondary } else { style::Button::Primary } )
                                 .padding(10),
                             )
+                            .push(
+                                Button::new(
+                                    Text::new(if *show_furigana {
+                                        "Hide furigana"
+                                    } else {
+                                        "Show furigana"
+                                    })
+                                    .width(Length::Fill)
+                                    .horizontal_alignment(Horizontal::Center)
+                                    .size(16),
+                                )
+                                .on_press(Message::ToggleFuriganaButtonPressed)
+                                .padding(10),
+                            )
                             .push(Text::new("Font size").size(30).width(Length::Shrink))
                             .push(
                                 slider(0..=40, *text_zoom_value, Message::TextSizeSliderChanged)
@@ -597,123 +2617,833 @@ impl Application for Dict {
                             .height(Length::Shrink)
                             .spacing(0)
                             .push(
-                                Row::new().push(
-                                    Text::new(reading.to_string())
-                                        .size(35)
-                                        .width(Length::FillPortion(4)),
-                                ),
+                                Row::new().push(
+                                    Text::new(with_romaji(reading, data.romaji_display))
+                                        .size(35)
+                                        .width(Length::FillPortion(4)),
+                                ),
+                            )
+                            .push(
+                                Row::new().push(
+                                    Text::new(word.to_string()).size(50).width(Length::Shrink),
+                                ),
+                            ),
+                    )
+                    .push(
+                        Row::new().push(
+                            Text::new(translations.clone().join(" / "))
+                                .size(35)
+                                .width(Length::FillPortion(1))
+                                .horizontal_alignment(Horizontal::Left),
+                        ),
+                    );
+
+                let kanji: Vec<&KanjiEntry> = kanjidic::kanji_in(word)
+                    .iter()
+                    .filter_map(|kanji| data.kanjidic.get(kanji))
+                    .collect();
+                if !kanji.is_empty() {
+                    column = column
+                        .push(Row::new().push(Space::new(Length::Fill, Length::Units(20))))
+                        .push(Text::new("Kanji:").size(30).width(Length::Fill));
+                    for entry in kanji {
+                        column = column.push(kanji_row(entry));
+                    }
+                } else if data.kanjidic_missing && !kanjidic::kanji_in(word).is_empty() {
+                    column = column.push(
+                        Text::new(format!(
+                            "Kanji panel unavailable: {} could not be read",
+                            kanjidic::KANJIDIC_FILE
+                        ))
+                        .size(16),
+                    );
+                }
+
+                if !stroke_orders.is_empty() {
+                    column = column
+                        .push(Row::new().push(Space::new(Length::Fill, Length::Units(20))))
+                        .push(
+                            Row::new()
+                                .spacing(10)
+                                .align_items(Alignment::Center)
+                                .push(Text::new("Stroke order:").size(30))
+                                .push(
+                                    Button::new(
+                                        Text::new(if *animate_strokes {
+                                            "Pause"
+                                        } else {
+                                            "Animate"
+                                        })
+                                        .size(16),
+                                    )
+                                    .padding(10)
+                                    .on_press(Message::AnimateStrokesButtonPressed),
+                                )
+                                .push(
+                                    Button::new(Text::new("Previous stroke").size(16))
+                                        .padding(10)
+                                        .on_press(Message::PreviousStrokeButtonPressed),
+                                )
+                                .push(
+                                    Button::new(Text::new("Next stroke").size(16))
+                                        .padding(10)
+                                        .on_press(Message::NextStrokeButtonPressed),
+                                ),
+                        )
+                        .push(stroke_order_row(stroke_orders, *stroke_step));
+                }
+
+                column = column
+                    .push(Row::new().push(Space::new(Length::Fill, Length::Units(20))))
+                    .push(
+                        Row::new()
+                            .spacing(20)
+                            .align_items(Alignment::Center)
+                            .push(
+                                Text::new(format!("{} sentence(s):", shown.len()))
+                                    .size(30)
+                                    .width(Length::Fill),
+                            )
+                            .push(
+                                PickList::new(
+                                    &SentenceOrder::ALL[..],
+                                    Some(data.sentence_order),
+                                    Message::SentenceOrderSelected,
+                                )
+                                .text_size(20),
+                            ),
+                    );
+
+                for (n, (sentence, difficulty, i_plus_one)) in shown.iter().enumerate() {
+                    let japanese_text = sentence_row(
+                        sentence,
+                        &readings,
+                        *show_furigana,
+                        30 + *text_zoom_value,
+                        word,
+                    );
+                    let japanese_row = Row::new()
+                        .spacing(20)
+                        .push(
+                            Text::new(if *selected_sentence == Some(n) {
+                                format!("> {}.", n + 1)
+                            } else {
+                                format!("{}.", n + 1)
+                            })
+                            .size(20 + *text_zoom_value)
+                            .width(Length::Shrink),
+                        )
+                        .push(japanese_text)
+                        .push(
+                            Text::new(format!(
+                                "{} ({}){}",
+                                Level::of(*difficulty),
+                                difficulty,
+                                if *i_plus_one { ", i+1" } else { "" }
+                            ))
+                            .size(16)
+                            .style(Color::new(0.67, 0.61, 0.60, 1.0)),
+                        );
+                    let english_row = Row::new().spacing(20).push(
+                        Text::new(sentence.english_text.clone())
+                            .size(30)
+                            .width(Length::Fill),
+                    );
+                    let spacing_row = Row::new().push(Space::new(Length::Fill, Length::Units(20)));
+                    column = column.push(japanese_row);
+                    if *toggle_show_translation {
+                        column = column.push(english_row);
+                    }
+                    column = column.push(spacing_row);
+                }
+
+                let scrollable = scrollable(Container::new(column).width(Length::Fill).center_x());
+
+                let modal = Modal::new(*show_modal, scrollable, || {
+                    Card::new(
+                        Text::new("Save Anki flash card"),
+                        Text::new(format!(
+                            "Added to pending cards ({} in total)",
+                            pending_cards.len()
+                        )),
+                    )
+                        .foot(
+                            Row::new()
+                                .spacing(10)
+                                .padding(5)
+                                .width(Length::Fill)
+                                .push(
+                                    Button::new(
+                                        Text::new("Ok").horizontal_alignment(Horizontal::Center),
+                                    )
+                                    // .style(style::Button::Primary)
+                                    .width(Length::Fill)
+                                    .on_press(Message::OkButtonPressed),
+                                )
+                                .push(
+                                    Button::new(
+                                        Text::new("Undo").horizontal_alignment(Horizontal::Center),
+                                    )
+                                    // .style(style::Button::Secondary)
+                                    .width(Length::Fill)
+                                    .on_press(Message::UndoButtonPressed),
+                                ),
+                        )
+                        .max_width(300)
+                        .on_close(Message::CloseModal)
+                        .into()
+                })
+                .backdrop(Message::CloseModal)
+                .on_esc(Message::CancelButtonPressed);
+
+                Container::new(modal)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(30)
+                    .into()
+            }
+            Dict::Pending {
+                status,
+                stroke_order_media,
+            } => {
+                let pending_cards = &data.pending_cards;
+                let mut column = Column::new()
+                    .align_items(Alignment::Start)
+                    .height(Length::Fill)
+                    .spacing(10)
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .push(
+                                Button::new(Text::new("Back").size(20))
+                                    .padding(10)
+                                    .on_press(Message::BackButtonPressed),
+                            )
+                            .push(
+                                Button::new(Text::new("Export to csv").size(16))
+                                    .padding(10)
+                                    .on_press(Message::ExportCsvButtonPressed),
+                            )
+                            .push(
+                                Button::new(Text::new("Export to .apkg").size(16))
+                                    .padding(10)
+                                    .on_press(Message::ExportApkgButtonPressed),
+                            )
+                            .push(
+                                Button::new(Text::new("Send to AnkiConnect").size(16))
+                                    .padding(10)
+                                    .on_press(Message::ExportAnkiConnectButtonPressed),
+                            )
+                            .push(
+                                Button::new(Text::new("Import from Anki").size(16))
+                                    .padding(10)
+                                    .on_press(Message::ImportAnkiButtonPressed),
+                            )
+                            .push(
+                                Button::new(Text::new("Cards from a word list").size(16))
+                                    .padding(10)
+                                    .on_press(Message::BatchButtonPressed),
+                            ),
+                    )
+                    .push(Checkbox::new(
+                        *stroke_order_media,
+                        "Add stroke order diagrams to .apkg cards",
+                        Message::StrokeOrderMediaToggled,
+                    ))
+                    .push(Text::new(status.clone()).size(20))
+                    .push(
+                        Text::new(format!("{} pending card(s):", pending_cards.len()))
+                            .size(30)
+                            .width(Length::Fill),
+                    );
+
+                for (n, card) in pending_cards.iter().enumerate() {
+                    let button = |label: &str, message: Message| {
+                        Button::new(Text::new(label.to_string()).size(16))
+                            .on_press(message)
+                            .padding(4)
+                    };
+                    let field = |placeholder: &str, value: &str, field: FlashcardField| {
+                        TextInput::new(placeholder, value, move |value| {
+                            Message::PendingCardEdited(n, field, value)
+                        })
+                        .padding(5)
+                        .size(20)
+                    };
+                    column = column
+                        .push(
+                            Row::new()
+                                .spacing(10)
+                                .align_items(Alignment::Center)
+                                .push(Text::new(format!("{}.", n + 1)).size(20))
+                                .push(Text::new(card.vocab.clone()).size(30))
+                                .push(Text::new(card.vocab_kana.clone()).size(20))
+                                .push(
+                                    Text::new(match data.anki_vocabulary.get(&card.vocab) {
+                                        Some(anki_word) => {
+                                            format!("already in Anki ({})", anki_word.state)
+                                        }
+                                        None => "".to_string(),
+                                    })
+                                    .size(16)
+                                    .style(Color::new(0.85, 0.45, 0.2, 1.0)),
+                                )
+                                .push(Space::new(Length::Fill, Length::Units(1)))
+                                .push(button("Up", Message::MovePendingCardUp(n)))
+                                .push(button("Down", Message::MovePendingCardDown(n)))
+                                .push(button(
+                                    "Other sentence",
+                                    Message::SwapPendingCardSentence(n),
+                                ))
+                                .push(button("Delete", Message::DeletePendingCard(n))),
+                        )
+                        .push(field(
+                            "Translation",
+                            &card.vocab_translation,
+                            FlashcardField::VocabTranslation,
+                        ))
+                        .push(field("Sentence", &card.sentence, FlashcardField::Sentence))
+                        .push(field(
+                            "Sentence translation",
+                            &card.sentence_translation,
+                            FlashcardField::SentenceTranslation,
+                        ))
+                        .push(field("Notes", &card.notes, FlashcardField::Notes))
+                        .push(Row::new().push(Space::new(Length::Fill, Length::Units(20))));
+                }
+
+                let scrollable = scrollable(Container::new(column).width(Length::Fill).center_x());
+
+                Container::new(scrollable)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(30)
+                    .into()
+            }
+            Dict::Batch {
+                file,
+                flagged_only,
+                status,
+            } => {
+                let cards = data
+                    .batch
+                    .iter()
+                    .filter(|entry| entry.card.is_some())
+                    .count();
+                let load_button = Button::new(Text::new("Load").size(16))
+                    .padding(10)
+                    .on_press(Message::LoadBatchButtonPressed);
+                let mut column = Column::new()
+                    .align_items(Alignment::Start)
+                    .height(Length::Fill)
+                    .spacing(10)
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(
+                                Button::new(Text::new("Back").size(20))
+                                    .padding(10)
+                                    .on_press(Message::BackButtonPressed),
+                            )
+                            .push(
+                                TextInput::new(
+                                    "Word list, one word per line",
+                                    file,
+                                    Message::BatchFileChanged,
+                                )
+                                .padding(10)
+                                .size(20),
+                            )
+                            .push(load_button)
+                            .push(
+                                Button::new(
+                                    Text::new(format!("Add {} to pending cards", cards)).size(16),
+                                )
+                                .padding(10)
+                                .on_press(Message::AddBatchCardsButtonPressed),
+                            ),
+                    )
+                    .push(Text::new(status.clone()).size(20))
+                    .push(
+                        Row::new()
+                            .spacing(20)
+                            .align_items(Alignment::Center)
+                            .push(Text::new(batch::progress(&data.batch)).size(20))
+                            .push(Checkbox::new(
+                                *flagged_only,
+                                "Only words that need a look",
+                                Message::FlaggedOnlyToggled,
+                            )),
+                    );
+
+                for (n, entry) in data.batch.iter().enumerate() {
+                    let issues = entry.issues();
+                    if *flagged_only && issues.is_empty() {
+                        continue;
+                    }
+                    let button = |label: &str, message: Message| {
+                        Button::new(Text::new(label.to_string()).size(16))
+                            .on_press(message)
+                            .padding(4)
+                    };
+                    let issues: Vec<String> = issues.iter().map(Issue::to_string).collect();
+                    let mut row = Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(Text::new(format!("{}.", n + 1)).size(20))
+                        .push(Text::new(entry.word.clone()).size(30));
+                    row = match entry.chosen_result() {
+                        Some(result) => row.push(
+                            Text::new(format!("{} 【{}】", result.japanese, result.reading))
+                                .size(20),
+                        ),
+                        None if entry.results.is_none() => {
+                            row.push(Text::new("looking up...").size(20))
+                        }
+                        None => row,
+                    };
+                    row = row
+                        .push(
+                            Text::new(issues.join(", "))
+                                .size(16)
+                                .style(Color::new(0.85, 0.45, 0.2, 1.0)),
+                        )
+                        .push(Space::new(Length::Fill, Length::Units(1)));
+                    if entry
+                        .results
+                        .as_ref()
+                        .is_some_and(|results| results.len() > 1)
+                    {
+                        row = row.push(button("Other entry", Message::NextBatchEntry(n)));
+                    }
+                    if entry.card.is_some() {
+                        row = row.push(button("Other sentence", Message::SwapBatchCardSentence(n)));
+                    }
+                    column = column.push(row.push(button("Delete", Message::DeleteBatchEntry(n))));
+
+                    if let Some(card) = &entry.card {
+                        let field = |placeholder: &str, value: &str, field: FlashcardField| {
+                            TextInput::new(placeholder, value, move |value| {
+                                Message::BatchCardEdited(n, field, value)
+                            })
+                            .padding(5)
+                            .size(20)
+                        };
+                        column = column
+                            .push(field(
+                                "Translation",
+                                &card.vocab_translation,
+                                FlashcardField::VocabTranslation,
+                            ))
+                            .push(field("Sentence", &card.sentence, FlashcardField::Sentence))
+                            .push(field(
+                                "Sentence translation",
+                                &card.sentence_translation,
+                                FlashcardField::SentenceTranslation,
+                            ));
+                    }
+                    column =
+                        column.push(Row::new().push(Space::new(Length::Fill, Length::Units(20))));
+                }
+
+                let scrollable = scrollable(Container::new(column).width(Length::Fill).center_x());
+
+                Container::new(scrollable)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(30)
+                    .into()
+            }
+            Dict::Favorites {
+                filter,
+                sort,
+                selected,
+                status,
+            } => {
+                let favorites = &data.favorites;
+                let mut column = Column::new()
+                    .align_items(Alignment::Start)
+                    .height(Length::Fill)
+                    .spacing(10)
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(
+                                Button::new(Text::new("Back").size(20))
+                                    .padding(10)
+                                    .on_press(Message::BackButtonPressed),
+                            )
+                            .push(
+                                TextInput::new(
+                                    "Filter...",
+                                    filter,
+                                    Message::FavoritesFilterChanged,
+                                )
+                                .padding(10)
+                                .size(20),
+                            )
+                            .push(PickList::new(
+                                &FavoritesSort::ALL[..],
+                                Some(*sort),
+                                Message::FavoritesSortSelected,
+                            ))
+                            .push(
+                                Button::new(Text::new("Select all").size(16))
+                                    .padding(10)
+                                    .on_press(Message::SelectAllFavoritesButtonPressed),
+                            )
+                            .push(
+                                Button::new(
+                                    Text::new(format!("Add {} to pending cards", selected.len()))
+                                        .size(16),
+                                )
+                                .padding(10)
+                                .on_press(Message::ExportFavoritesButtonPressed),
+                            ),
+                    )
+                    .push(Text::new(status.clone()).size(20));
+
+                let shown = favorites::filter_and_sort(favorites, filter, *sort);
+                column = column.push(
+                    Text::new(format!("{} favorite(s):", shown.len()))
+                        .size(30)
+                        .width(Length::Fill),
+                );
+                for index in shown {
+                    let favorite = &favorites[index];
+                    let key = (favorite.word.clone(), favorite.reading.clone());
+                    column = column.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(Checkbox::new(
+                                selected.contains(&key),
+                                "",
+                                move |is_selected| {
+                                    let (word, reading) = key.clone();
+                                    Message::FavoriteSelected(word, reading, is_selected)
+                                },
+                            ))
+                            .push(
+                                Button::new(Text::new("★").size(16))
+                                    .on_press(Message::ToggleFavoriteButtonPressed(
+                                        favorite.word.clone(),
+                                        favorite.reading.clone(),
+                                        favorite.translations.clone(),
+                                    ))
+                                    .padding(4),
+                            )
+                            .push(
+                                Text::new(favorite.word.clone())
+                                    .size(30)
+                                    .width(Length::Fill),
+                            )
+                            .push(
+                                Text::new(favorite.reading.clone())
+                                    .size(30)
+                                    .width(Length::Fill),
+                            )
+                            .push(
+                                Text::new(favorite.translations.join(" / "))
+                                    .size(20)
+                                    .width(Length::Fill),
+                            ),
+                    );
+                }
+
+                let scrollable = scrollable(Container::new(column).width(Length::Fill).center_x());
+
+                Container::new(scrollable)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(30)
+                    .into()
+            }
+            Dict::Study {
+                queue,
+                toggle_show_translation,
+                ..
+            } => {
+                let mut column = Column::new()
+                    .align_items(Alignment::Start)
+                    .height(Length::Fill)
+                    .spacing(10)
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(
+                                Button::new(Text::new("Back").size(20))
+                                    .padding(10)
+                                    .on_press(Message::BackButtonPressed),
                             )
-                            .push(
-                                Row::new().push(
-                                    Text::new(word.to_string()).size(50).width(Length::Shrink),
-                                ),
-                            ),
-                    )
-                    .push(
-                        Row::new().push(
-                            Text::new(translations.clone().join(" / "))
-                                .size(35)
-                                .width(Length::FillPortion(1))
-                                .horizontal_alignment(Horizontal::Left),
-                        ),
-                    )
-                    .push(Row::new().push(Space::new(Length::Fill, Length::Units(20))))
-                    .push(
-                        Text::new(format!(
-                            "{} sentence(s):",
-                            std::cmp::min(sentences.len(), 20_usize)
-                        ))
-                        .size(30)
-                        .width(Length::Fill),
+                            .push(Text::new(format!("{} card(s) left", queue.len())).size(20)),
                     );
 
-                for (n, sentence) in sentences.iter().take(20).enumerate() {
-                    let japanese_row = Row::new()
-                        .spacing(20)
-                        .push(
-                            Text::new(format!("{}.", n))
-                                .size(20 + *text_zoom_value)
-                                .width(Length::Shrink),
-                        )
-                        .push(
-                            Text::new(sentence.japanese_text.clone())
-                                .size(30 + *text_zoom_value)
-                                .width(Length::Fill),
-                        );
-                    let english_row = Row::new().spacing(20).push(
-                        Text::new(sentence.english_text.clone())
-                            .size(30)
-                            .width(Length::Fill),
-                    );
-                    let spacing_row = Row::new().push(Space::new(Length::Fill, Length::Units(20)));
-                    column = column.push(japanese_row);
-                    if *toggle_show_translation {
-                        column = column.push(english_row);
+                match queue.first() {
+                    None => {
+                        column = column.push(Text::new("Nothing to study right now!").size(40));
+                    }
+                    Some(card) => {
+                        column = column.push(Text::new(card.word.clone()).size(60));
+                        if *toggle_show_translation {
+                            column = column
+                                .push(Text::new(card.reading.clone()).size(35))
+                                .push(Text::new(card.translation.clone()).size(35))
+                                .push(Row::new().push(Space::new(Length::Fill, Length::Units(20))))
+                                .push(Text::new(card.sentence.clone()).size(30))
+                                .push(Text::new(card.sentence_translation.clone()).size(25))
+                                .push(Grade::ALL.iter().enumerate().fold(
+                                    Row::new().spacing(10),
+                                    |row, (n, grade)| {
+                                        row.push(
+                                            Button::new(
+                                                Text::new(format!("{} ({})", grade.label(), n + 1))
+                                                    .size(20),
+                                            )
+                                            .padding(10)
+                                            .on_press(Message::GradeButtonPressed(*grade)),
+                                        )
+                                    },
+                                ));
+                        } else {
+                            column = column.push(
+                                Button::new(Text::new("Show answer").size(20))
+                                    .padding(10)
+                                    .on_press(Message::ToggleShowTranslationButtonPressed),
+                            );
+                        }
                     }
-                    column = column.push(spacing_row);
                 }
 
-                let scrollable = scrollable(Container::new(column).width(Length::Fill).center_x());
-
-                let modal = Modal::new(*show_modal, scrollable, || {
-                    Card::new(Text::new("Save Anki flash card"), Text::new("Saved!"))
-                        .foot(
+                Container::new(column)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(30)
+                    .into()
+            }
+            Dict::Help { .. } => {
+                let mut column = Column::new()
+                    .align_items(Alignment::Start)
+                    .spacing(10)
+                    .push(
+                        Button::new(Text::new("Back").size(20))
+                            .padding(10)
+                            .on_press(Message::BackButtonPressed),
+                    )
+                    .push(Text::new("Keyboard shortcuts").size(40));
+                let keymap = &data.keymap;
+                let sections = std::iter::once((None, "Everywhere")).chain(
+                    Screen::ALL
+                        .iter()
+                        .map(|screen| (Some(*screen), screen.name())),
+                );
+                for (screen, name) in sections {
+                    let bindings = keymap.describe(screen);
+                    if bindings.is_empty() {
+                        continue;
+                    }
+                    column = column.push(Text::new(name).size(30));
+                    for (action, keys) in bindings {
+                        column = column.push(
                             Row::new()
-                                .spacing(10)
-                                .padding(5)
-                                .width(Length::Fill)
+                                .spacing(20)
                                 .push(
-                                    Button::new(
-                                        Text::new("Ok").horizontal_alignment(Horizontal::Center),
-                                    )
-                                    // .style(style::Button::Primary)
-                                    .width(Length::Fill)
-                                    .on_press(Message::OkButtonPressed),
+                                    Text::new(keys.join(", "))
+                                        .size(25)
+                                        .width(Length::Units(200)),
                                 )
-                                .push(
-                                    Button::new(
-                                        Text::new("Undo").horizontal_alignment(Horizontal::Center),
-                                    )
-                                    // .style(style::Button::Secondary)
-                                    .width(Length::Fill)
-                                    .on_press(Message::UndoButtonPressed),
-                                ),
-                        )
-                        .max_width(300)
-                        .on_close(Message::CloseModal)
-                        .into()
-                })
-                .backdrop(Message::CloseModal)
-                .on_esc(Message::CancelButtonPressed);
+                                .push(Text::new(action.description()).size(25)),
+                        );
+                    }
+                }
+                column = column
+                    .push(
+                        Row::new()
+                            .spacing(20)
+                            .push(Text::new("1 - 9").size(25).width(Length::Units(200)))
+                            .push(Text::new("Pick an example sentence / grade a card").size(25)),
+                    )
+                    .push(
+                        Text::new(format!(
+                            "Keys can be changed in {}, e.g. {{ \"key_bindings\": {{ \"N\": \"next\" }} }}",
+                            config::CONFIG_FILE
+                        ))
+                        .size(18),
+                    );
 
-                Container::new(modal)
+                Container::new(scrollable(column))
                     .width(Length::Fill)
                     .height(Length::Fill)
                     .padding(30)
                     .into()
             }
+        }
+    }
+}
+
+impl Dict {
+    fn screen(&self) -> Option<Screen> {
+        match self {
+            Dict::Startup {} => None,
+            Dict::Waiting { .. } => Some(Screen::Search),
+            Dict::Loading { .. } => Some(Screen::Loading),
+            Dict::Loaded { .. } => Some(Screen::Results),
+            Dict::Sentences { .. } => Some(Screen::Sentences),
+            Dict::Grammar { .. } => Some(Screen::Grammar),
+            Dict::Analysis { .. } => Some(Screen::Analysis),
+            Dict::Details { .. } => Some(Screen::Details),
+            Dict::Pending { .. } => Some(Screen::PendingCards),
+            Dict::Batch { .. } => Some(Screen::Batch),
+            Dict::Favorites { .. } => Some(Screen::Favorites),
+            Dict::Study { .. } => Some(Screen::Study),
+            Dict::Help {} => Some(Screen::Help),
+        }
+    }
+
+    // Screens a copied word is looked up from. Not the ones where cards or text are
+    // edited, nor the startup screen, which has no sentences yet.
+    fn follows_clipboard(&self) -> bool {
+        matches!(
+            self,
+            Dict::Waiting { .. }
+                | Dict::Loading { .. }
+                | Dict::Loaded { .. }
+                | Dict::Sentences { .. }
+                | Dict::Grammar { .. }
+                | Dict::Details { .. }
+                | Dict::Help {}
+        )
+    }
+
+    fn waiting(data: &Data) -> Dict {
+        Dict::Waiting {
+            input_value: "".to_string(),
+            radical_picker: None,
+            due_count: study_due_count(data),
+            history_cursor: None,
+            show_modal: false,
+            status: "".to_string(),
+        }
+    }
+
+    // The browser with the given pattern open, and the sentences it matches.
+    fn grammar(data: &Data, selected: usize) -> Dict {
+        let matches = match data.grammar.get(selected) {
+            Some(pattern) => grammar::matching_sentences(
+                &pattern.regex,
+                data.sentence_index.sentences(),
+                MAX_GRAMMAR_SENTENCES,
+            ),
+            None => Vec::new(),
         };
+        Dict::Grammar {
+            selected,
+            matches,
+            selected_sentence: 0,
+            status: "".to_string(),
+        }
     }
 
-    fn subscription(&self) -> Subscription<Message> {
-        subscription::events_with(|event, _status| {
-            // this can be used to not handle the event when cursor is inside an input box
-            // if let event::Status::Captured = status {
-            //     return None;
-            // }
-
-            match event {
-                Event::Keyboard(keyboard::Event::KeyPressed {
-                    modifiers: _,
-                    key_code,
-                }) => handle_hotkey(key_code),
-                _ => None,
+    // The detail page of a word, and the command that reads its stroke order diagrams.
+    fn details(
+        data: &Data,
+        word: String,
+        reading: String,
+        translations: Vec<String>,
+        search_results: Vec<SearchResult>,
+    ) -> (Dict, Command<Message>) {
+        let loaded_word = word.clone();
+        let command = Command::perform(Dict::load_stroke_orders(word.clone()), move |loaded| {
+            Message::StrokeOrdersLoaded(loaded_word.clone(), loaded)
+        });
+        let details = Dict::Details {
+            stroke_orders: Vec::new(),
+            sentences: shown_sentences(data, &word),
+            word,
+            reading,
+            translations,
+            toggle_show_translation: false,
+            show_furigana: false,
+            search_results,
+            text_zoom_value: 18,
+            selected_sentence: None,
+            show_modal: false,
+            stroke_step: None,
+            animate_strokes: false,
+        };
+        (details, command)
+    }
+
+    // Brings a screen that's shown again (with Back or Forward) up to date.
+    fn refresh(&mut self, data: &Data) {
+        match self {
+            Dict::Waiting {
+                due_count,
+                history_cursor,
+                show_modal,
+                status,
+                ..
+            } => {
+                *due_count = study_due_count(data);
+                *history_cursor = None;
+                *show_modal = false;
+                status.clear();
             }
-        })
+            Dict::Details {
+                word,
+                sentences,
+                selected_sentence,
+                show_modal,
+                ..
+            } => {
+                // words may have been marked as known in the meantime
+                let shown = shown_sentences(data, word);
+                if shown != *sentences {
+                    *sentences = shown;
+                    *selected_sentence = None;
+                }
+                *show_modal = false;
+            }
+            _ => {}
+        }
+    }
+
+    // Looks up the next word of the batch that hasn't been, one at a time to go easy
+    // on jisho. Words added while a lookup is under way are looked up after it.
+    fn batch_command(data: &mut Data) -> Command<Message> {
+        if data.batch_looking_up {
+            return Command::none();
+        }
+        match data.batch.iter().find(|entry| entry.results.is_none()) {
+            Some(entry) => {
+                data.batch_looking_up = true;
+                let word = entry.word.clone();
+                Command::perform(Dict::search(word.clone()), move |result| {
+                    Message::BatchWordFound(word.clone(), result)
+                })
+            }
+            None => Command::none(),
+        }
+    }
+
+    // The loading screen of a new search, and the command that sends it.
+    fn loading(data: &mut Data, query: String, open_details: bool) -> (Dict, Command<Message>) {
+        data.last_search += 1;
+        let search = data.last_search;
+        let command = Command::perform(Dict::search(query.clone()), move |result| {
+            Message::WordFound(search, result)
+        });
+        let loading = Dict::Loading {
+            query,
+            open_details,
+            search,
+        };
+        (loading, command)
     }
-}
 
-impl Dict {
     async fn search(query: String) -> Result<JishoResponse, DictError> {
         let jisho_base_url = "https://jisho.org/api/v1/search/words?keyword=".to_string();
         let resp: JishoResponse = reqwest::get(jisho_base_url + &query[..])
@@ -743,7 +3473,57 @@ impl Dict {
         Ok(contents)
     }
 
-    fn parse_example_sentences(sentences: String) -> SentenceMap {
+    async fn load_kanjidic() -> Result<Kanjidic, DictError> {
+        let xml = async_std::fs::read_to_string(kanjidic::KANJIDIC_FILE)
+            .await
+            .map_err(|_| DictError::FileNotFound)?;
+        kanjidic::parse_kanjidic(&xml).map_err(|_| DictError::ParseFile)
+    }
+
+    // The stroke order diagrams of a word, read in the background for its detail page.
+    async fn load_stroke_orders(word: String) -> Vec<StrokeOrder> {
+        let mut stroke_orders = Vec::new();
+        for kanji in kanjidic::kanji_in(&word) {
+            if let Ok(svg) = async_std::fs::read_to_string(kanjivg::kanjivg_file(kanji)).await {
+                stroke_orders.extend(kanjivg::parse_kanjivg(kanji, &svg).ok());
+            }
+        }
+        stroke_orders
+    }
+
+    async fn load_radicals() -> Result<Radicals, DictError> {
+        let radkfile = async_std::fs::read(radicals::RADKFILE)
+            .await
+            .map_err(|_| DictError::FileNotFound)?;
+        // the kradfile only makes narrowing down faster, it's fine without
+        let kradfile = async_std::fs::read(radicals::KRADFILE)
+            .await
+            .ok()
+            .map(|kradfile| radicals::parse_kradfile(&radicals::decode_euc_jp(&kradfile)));
+        Ok(Radicals::new(
+            radicals::parse_radkfile(&radicals::decode_euc_jp(&radkfile)),
+            kradfile,
+        ))
+    }
+
+    // Sends the cards to Anki, returns the ones it added.
+    async fn export_to_anki_connect(cards: Vec<Flashcard>) -> Result<Vec<Flashcard>, DictError> {
+        let request = flashcards::anki_connect_add_notes_request(&cards);
+        let response: serde_json::Value = reqwest::Client::new()
+            .post(flashcards::ANKI_CONNECT_URL)
+            .json(&request)
+            .send()
+            .await?
+            .json()
+            .await?;
+        // e.g. a deck or note type that doesn't exist
+        if let Some(error) = response["error"].as_str() {
+            return Err(DictError::AnkiConnect(error.to_string()));
+        }
+        Ok(flashcards::anki_connect_added(&cards, &response))
+    }
+
+    fn parse_example_sentences(sentences: String) -> (SentenceMap, SentenceIndex) {
         // http://www.edrdg.org/wiki/index.php/Sentence-Dictionary_Linking
         // a little pre-processing for dirtiness in the wwwjdict data
         let sentences = sentences.replace("	 ", "	"); // tab + space becomes just tab
@@ -764,19 +3544,20 @@ impl Dict {
         );
         let start_indexing = std::time::SystemTime::now();
         let mut words_to_sentences: HashMap<String, Vec<ExampleSentence>> = HashMap::new();
-        for sentence in parsed {
+        for sentence in &parsed {
             for index_word in &sentence.indices {
                 words_to_sentences
                     .entry(index_word.headword.to_owned())
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(sentence.to_owned());
             }
         }
+        let sentence_index = SentenceIndex::new(parsed);
         println!(
             "indexing example sentences took: {} milliseconds",
             start_indexing.elapsed().unwrap().as_millis()
         );
-        words_to_sentences
+        (words_to_sentences, sentence_index)
     }
 }
 
@@ -785,6 +3566,9 @@ enum DictError {
     SearchApi,
     FileNotFound,
     ReadFile,
+    ParseFile,
+    // what Anki answered when it refused the request
+    AnkiConnect(String),
 }
 
 impl From<reqwest::Error> for DictError {
@@ -794,62 +3578,544 @@ impl From<reqwest::Error> for DictError {
     }
 }
 
-fn handle_hotkey(key_code: keyboard::KeyCode) -> Option<Message> {
-    use keyboard::KeyCode;
+// The example sentences of a word as the detail page lists them, with their difficulty
+// and whether the word is the only one in them the user doesn't know. Those come first.
+fn shown_sentences(data: &Data, word: &str) -> Vec<(ExampleSentence, u32, bool)> {
+    let sentences = match data.example_sentences.get(word) {
+        Some(sentences) => sentences.as_slice(),
+        None => return Vec::new(),
+    };
+    let mut shown: Vec<(&ExampleSentence, u32, bool)> = difficulty::order_sentences(
+        sentences,
+        data.sentence_order,
+        &data.example_sentences,
+        &data.kanjidic,
+    )
+    .into_iter()
+    .map(|(i, difficulty)| {
+        let sentence = &sentences[i];
+        let i_plus_one = known_words::is_i_plus_one(sentence, word, &data.known_words);
+        (sentence, difficulty, i_plus_one)
+    })
+    .collect();
+    shown.sort_by_key(|&(_, _, i_plus_one)| !i_plus_one);
+    shown.truncate(MAX_SENTENCES);
+    shown
+        .into_iter()
+        .map(|(sentence, difficulty, i_plus_one)| (sentence.clone(), difficulty, i_plus_one))
+        .collect()
+}
+
+// The sentence a new card gets, picked by the configured policy.
+fn card_sentence(data: &Data, word: &str) -> ExampleSentence {
+    let sentences = data
+        .example_sentences
+        .get(word)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let picked = match data.sentence_policy {
+        SentencePolicy::IPlusOne => {
+            known_words::i_plus_one_sentence(sentences, word, &data.known_words)
+        }
+        SentencePolicy::Shortest => None,
+        SentencePolicy::Easiest => {
+            difficulty::easiest_sentence(sentences, &data.example_sentences, &data.kanjidic)
+        }
+    };
+    match picked {
+        Some(sentence) => sentence.clone(),
+        None => shortest_sentence(sentences),
+    }
+}
+
+// Gives the card the word's next example sentence, with furigana if it had them.
+fn next_card_sentence(card: &mut Flashcard, example_sentences: &SentenceMap) {
+    let plain_sentence = furigana::strip_anki_furigana(&card.sentence);
+    let with_furigana = plain_sentence != card.sentence;
+    let sentences = example_sentences
+        .get(&card.vocab)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    if sentences.is_empty() {
+        return;
+    }
+    let next = sentences
+        .iter()
+        .position(|s| s.japanese_text == plain_sentence)
+        .map(|current| (current + 1) % sentences.len())
+        .unwrap_or(0);
+    card.sentence = if with_furigana {
+        let readings = HashMap::from([(card.vocab.clone(), card.vocab_kana.clone())]);
+        furigana::to_anki_furigana(&furigana::sentence_furigana(&sentences[next], &readings))
+    } else {
+        sentences[next].japanese_text.clone()
+    };
+    card.sentence_translation = sentences[next].english_text.clone();
+}
+
+fn shortest_sentence(sentences: &[ExampleSentence]) -> ExampleSentence {
+    sentences
+        .iter()
+        .min_by(|s1, s2| s1.english_text.len().cmp(&s2.english_text.len()))
+        .cloned()
+        .unwrap_or_default()
+}
+
+// Starts watching the clipboard if that's turned on, turns it off when there's no
+// clipboard to watch.
+fn watch_clipboard(data: &mut Data) -> Option<ClipboardWatcher<SystemClipboard>> {
+    data.clipboard_error = None;
+    if !data.clipboard.enabled {
+        return None;
+    }
+    match SystemClipboard::new() {
+        Ok(system_clipboard) => Some(ClipboardWatcher::new(system_clipboard, &data.clipboard)),
+        Err(error) => {
+            data.clipboard.enabled = false;
+            data.clipboard_error = Some(error.to_string());
+            None
+        }
+    }
+}
+
+// Segments the text with every word we have sentences for, or the user knows, as the
+// dictionary.
+fn analyze_text(data: &Data, text: &str) -> Analysis {
+    text_analysis::analyze(text, |word| {
+        data.example_sentences.contains_key(word)
+            || data.known_words.contains(word)
+            || data.anki_vocabulary.contains_key(word)
+    })
+}
+
+// How far the user is with a word of an analyzed text.
+fn vocabulary_status(data: &Data, word: &str) -> String {
+    if data.known_words.contains(word) {
+        return "known".to_string();
+    }
+    match data.anki_vocabulary.get(word) {
+        Some(anki_word) => format!("in Anki ({})", anki_word.state),
+        None => "new".to_string(),
+    }
+}
+
+// Makes the card of a batch entry from its chosen result, with a sentence picked by
+// the configured policy.
+fn fill_batch_card(data: &mut Data, index: usize) {
+    let Some(result) = data.batch[index].chosen_result().cloned() else {
+        data.batch[index].card = None;
+        return;
+    };
+    let sentence = match &data.batch[index].context {
+        Some(context) => ExampleSentence {
+            japanese_text: context.clone(),
+            ..ExampleSentence::default()
+        },
+        None => card_sentence(data, &result.japanese),
+    };
+    let furigana = data.card_furigana;
+    let entry = &mut data.batch[index];
+    entry.sentences = result.ranking.frequency;
+    entry.card = Some(new_flashcard(
+        &result.japanese,
+        &result.reading,
+        &result.translations,
+        sentence,
+        furigana,
+    ));
+}
+
+// `furigana` gives the sentence readings in Anki's format.
+fn new_flashcard(
+    word: &str,
+    reading: &str,
+    translations: &[String],
+    sentence: ExampleSentence,
+    furigana: bool,
+) -> Flashcard {
+    let japanese_text = if furigana {
+        let readings = HashMap::from([(word.to_string(), reading.to_string())]);
+        furigana::to_anki_furigana(&furigana::sentence_furigana(&sentence, &readings))
+    } else {
+        sentence.japanese_text
+    };
+    Flashcard {
+        vocab: word.to_string(),
+        vocab_kana: reading.to_string(),
+        vocab_translation: translations.join(" / "),
+        part_of_speech: "TODO".to_string(),
+        sentence: japanese_text,
+        sentence_translation: sentence.english_text,
+        notes: "".to_string(),
+    }
+}
+
+// Queues a flash card for export and adds its word to the study deck.
+fn save_flashcard(
+    pending_cards: &mut Vec<Flashcard>,
+    database: Option<&Connection>,
+    card: Flashcard,
+) {
+    if let Some(database) = database {
+        let study_card = StudyCard::new(
+            card.vocab.clone(),
+            card.vocab_kana.clone(),
+            card.vocab_translation.clone(),
+            furigana::strip_anki_furigana(&card.sentence),
+            card.sentence_translation.clone(),
+            srs::now(),
+        );
+        let _ = srs::add_card(database, &study_card);
+    }
+    pending_cards.push(card);
+}
 
-    match key_code {
-        KeyCode::Enter | KeyCode::NumpadEnter => Some(Message::SearchButtonPressed),
-        KeyCode::Escape => Some(Message::EscapeButtonPressed),
-        KeyCode::Q => Some(Message::QButtonPressed),
-        KeyCode::T => Some(Message::TButtonPressed),
-        _ => None,
+// Takes a pending card back, and its word out of the study deck unless it has been
+// studied already or another pending card has it.
+fn discard_flashcard(
+    pending_cards: &mut Vec<Flashcard>,
+    database: Option<&Connection>,
+    index: usize,
+) {
+    let card = pending_cards.remove(index);
+    if pending_cards.iter().any(|other| other.vocab == card.vocab) {
+        return;
     }
+    if let Some(database) = database {
+        let _ = srs::remove_new_card(database, &card.vocab);
+    }
+}
+
+// A column header of the search results, with an arrow on the column they're sorted by.
+fn sort_header<'a>(label: &str, sort: ResultsSort, order: ResultsOrder) -> Button<'a, Message> {
+    let label = match (order.sort == sort, order.reversed) {
+        (true, false) => format!("{} ▼", label),
+        (true, true) => format!("{} ▲", label),
+        (false, _) => label.to_string(),
+    };
+    Button::new(Text::new(label).size(20))
+        .padding(4)
+        .style(theme::Button::Text)
+        .on_press(Message::ResultsSortPressed(sort))
+}
+
+// Indices of the search results to show, in the order to show them in.
+fn shown_results(search_results: &[SearchResult], order: ResultsOrder) -> Vec<usize> {
+    let rankings: Vec<Ranking> = search_results.iter().map(|result| result.ranking).collect();
+    order.filter_and_sort(&rankings)
+}
+
+fn toggle_favorite(
+    favorites: &mut Vec<Favorite>,
+    word: String,
+    reading: String,
+    translations: Vec<String>,
+) {
+    favorites::toggle(
+        favorites,
+        Favorite {
+            word,
+            reading,
+            translations,
+            added: srs::now(),
+        },
+    );
+    let _ = favorites::store_favorites(favorites);
+}
+
+fn study_due_count(data: &Data) -> usize {
+    data.study_database
+        .as_ref()
+        .and_then(|database| srs::due_count(database, srs::now()).ok())
+        .unwrap_or_default()
+}
+
+// Headword -> reading for every dictionary entry we know of, used for furigana of
+// index words that come without a reading.
+fn dictionary_readings(
+    search_results: &[SearchResult],
+    word: &str,
+    reading: &str,
+) -> HashMap<String, String> {
+    let mut readings: HashMap<String, String> = search_results
+        .iter()
+        .map(|result| (result.japanese.clone(), result.reading.clone()))
+        .collect();
+    readings.insert(word.to_string(), reading.to_string());
+    readings
+}
+
+// An example sentence where every index word (except the one on this page) can be
+// clicked to look it up.
+fn sentence_row<'a>(
+    sentence: &ExampleSentence,
+    readings: &HashMap<String, String>,
+    show_furigana: bool,
+    size: u16,
+    current_word: &str,
+) -> Row<'a, Message> {
+    split_on_index_words(sentence).into_iter().fold(
+        Row::new().align_items(Alignment::End),
+        |row, part| {
+            let link = part.word.filter(|word| word.headword != current_word);
+            let content: Element<Message> = if show_furigana {
+                let segments = match part.word {
+                    Some(word) => furigana::index_word_furigana(part.text, word, readings),
+                    None => vec![FuriganaSegment {
+                        text: part.text.to_string(),
+                        reading: None,
+                    }],
+                };
+                furigana_row(segments, size).into()
+            } else if link.is_some() {
+                Text::new(part.text.to_string())
+                    .size(size)
+                    .style(Color::new(0.2, 0.4, 0.8, 1.0))
+                    .into()
+            } else {
+                Text::new(part.text.to_string()).size(size).into()
+            };
+            match link {
+                Some(word) => row.push(
+                    Button::new(content)
+                        .padding(0)
+                        .style(theme::Button::Text)
+                        .on_press(Message::SentenceWordPressed(
+                            word.headword.clone(),
+                            word.reading.clone(),
+                        )),
+                ),
+                None => row.push(content),
+            }
+        },
+    )
+}
+
+fn search_tab_button<'a>(label: &str, tab: SearchTab, current: SearchTab) -> Button<'a, Message> {
+    let style = if tab == current {
+        theme::Button::Primary
+    } else {
+        theme::Button::Secondary
+    };
+    Button::new(Text::new(label.to_string()).size(18))
+        .padding(8)
+        .style(style)
+        .on_press(Message::SearchTabSelected(tab))
 }
 
-#[derive(Serialize)]
-struct BasicJapaneseFlashcard<'a> {
-    vocab: &'a str,
-    vocab_kana: &'a str,
-    vocab_translation: &'a str,
-    part_of_speech: &'a str,
-    sentence: &'a str,
-    sentence_translation: &'a str,
+// The text with the given byte ranges in color.
+fn highlighted_text<'a>(
+    text: &str,
+    highlights: &[std::ops::Range<usize>],
+    size: u16,
+) -> Element<'a, Message> {
+    let mut row = Row::new();
+    let mut cursor = 0;
+    for range in highlights {
+        if range.start < cursor {
+            continue;
+        }
+        if range.start > cursor {
+            row = row.push(Text::new(text[cursor..range.start].to_string()).size(size));
+        }
+        row = row.push(
+            Text::new(text[range.clone()].to_string())
+                .size(size)
+                .style(Color::new(0.85, 0.45, 0.2, 1.0)),
+        );
+        cursor = range.end;
+    }
+    if cursor < text.len() {
+        row = row.push(Text::new(text[cursor..].to_string()).size(size));
+    }
+    row.into()
 }
 
-fn store_word_to_csv<T: Serialize>(card: &T) -> Result<(), Box<dyn Error>> {
-    let file = std::fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .append(true)
-        .open("japanese_words_anki_import.txt")
-        .unwrap();
-    let mut wtr = csv::WriterBuilder::new()
-        .has_headers(false)
-        .from_writer(file);
-    wtr.serialize(card)?;
-    Ok(())
+// e.g. "とうきょう (tōkyō)"
+fn with_romaji(reading: &str, romaji_display: RomajiDisplay) -> String {
+    match romaji_display.system() {
+        Some(system) => format!("{} ({})", reading, romaji::to_romaji(reading, system)),
+        None => reading.to_string(),
+    }
 }
 
-fn delete_last_line_of_csv() -> Result<(), Box<dyn Error>> {
-    let mut file = std::fs::OpenOptions::new()
-        .read(true)
-        .open("japanese_words_anki_import.txt")?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
+const RADICALS_PER_ROW: usize = 24;
+const MAX_RADICAL_KANJI: usize = 240;
+
+// Radicals by stroke count, and the kanji that have all the selected ones, also by
+// stroke count. Radicals that wouldn't leave any kanji can't be picked.
+fn radical_picker_view<'a>(
+    radicals: &Radicals,
+    kanjidic: &Kanjidic,
+    selected: &[char],
+) -> Element<'a, Message> {
+    let available = radicals.available_radicals(selected);
+    let mut radical_rows = Column::new().spacing(2);
+    for (stroke_count, group) in radicals.by_stroke_count() {
+        for (n, chunk) in group.chunks(RADICALS_PER_ROW).enumerate() {
+            let label = if n == 0 {
+                stroke_count.to_string()
+            } else {
+                "".to_string()
+            };
+            let mut row = Row::new()
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .push(Text::new(label).size(16).width(Length::Units(25)));
+            for radical in chunk {
+                let style = if selected.contains(radical) {
+                    theme::Button::Primary
+                } else {
+                    theme::Button::Secondary
+                };
+                let button = Button::new(Text::new(radical.to_string()).size(20))
+                    .padding(2)
+                    .style(style);
+                row = row.push(
+                    if available.contains(radical) || selected.contains(radical) {
+                        button.on_press(Message::RadicalPressed(*radical))
+                    } else {
+                        button
+                    },
+                );
+            }
+            radical_rows = radical_rows.push(row);
+        }
+    }
+
+    let matching = radicals.matching_kanji(selected);
+    let mut by_stroke_count: BTreeMap<u32, Vec<char>> = BTreeMap::new();
+    for kanji in matching.iter().take(MAX_RADICAL_KANJI) {
+        let stroke_count = kanjidic
+            .get(kanji)
+            .and_then(|entry| entry.stroke_count)
+            .unwrap_or_default();
+        by_stroke_count
+            .entry(stroke_count)
+            .or_default()
+            .push(*kanji);
+    }
+    let mut kanji_rows = Column::new().spacing(2);
+    for (stroke_count, group) in by_stroke_count {
+        for (n, chunk) in group.chunks(RADICALS_PER_ROW / 2).enumerate() {
+            // 0 is for kanji the kanjidic doesn't know
+            let label = match (n, stroke_count) {
+                (0, 0) => "?".to_string(),
+                (0, _) => stroke_count.to_string(),
+                _ => "".to_string(),
+            };
+            let mut row = Row::new()
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .push(Text::new(label).size(16).width(Length::Units(25)));
+            for kanji in chunk {
+                row = row.push(
+                    Button::new(Text::new(kanji.to_string()).size(30))
+                        .padding(2)
+                        .style(theme::Button::Text)
+                        .on_press(Message::RadicalKanjiPressed(*kanji)),
+                );
+            }
+            kanji_rows = kanji_rows.push(row);
+        }
+    }
 
-    let lines = contents.lines();
-    let mut all_but_last: Vec<&str> = lines.rev().skip(1).collect();
-    all_but_last.reverse();
+    let header = if selected.is_empty() {
+        "Pick the parts of the kanji you're looking for:".to_string()
+    } else if matching.len() > MAX_RADICAL_KANJI {
+        format!(
+            "{} kanji, showing the first {}:",
+            matching.len(),
+            MAX_RADICAL_KANJI
+        )
+    } else {
+        format!("{} kanji:", matching.len())
+    };
+    Column::new()
+        .spacing(10)
+        .push(
+            Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(Text::new(header).size(20))
+                .push(
+                    Button::new(Text::new("Clear").size(16))
+                        .padding(5)
+                        .on_press(Message::ClearRadicalsButtonPressed),
+                ),
+        )
+        .push(
+            Row::new()
+                .spacing(20)
+                .push(scrollable(radical_rows).height(Length::Units(300)))
+                .push(scrollable(kanji_rows).height(Length::Units(300))),
+        )
+        .into()
+}
 
-    let file = std::fs::OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open("japanese_words_anki_import.txt")?;
-    let mut buf_writer = std::io::BufWriter::new(file);
-    for line in all_but_last {
-        writeln!(buf_writer, "{}", line)?;
+// The word is written one kanji after the other, `step` counts the strokes drawn so far.
+fn stroke_order_row<'a>(stroke_orders: &[StrokeOrder], step: Option<usize>) -> Row<'a, Message> {
+    let mut row = Row::new().spacing(10);
+    let mut written = 0;
+    for stroke_order in stroke_orders {
+        let count = stroke_order.stroke_count();
+        let image = match step {
+            Some(step) => {
+                let drawn = step.saturating_sub(written).min(count);
+                stroke_order.svg(drawn, step > written && step <= written + count)
+            }
+            None => stroke_order.svg(count, false),
+        };
+        written += count;
+        row = row.push(
+            Svg::new(svg::Handle::from_memory(image.into_bytes()))
+                .width(Length::Units(150))
+                .height(Length::Units(150)),
+        );
     }
-    Ok(())
+    row
+}
+
+// A kanji with its readings and meanings, click it to search words that use it.
+fn kanji_row<'a>(entry: &KanjiEntry) -> Row<'a, Message> {
+    let readings = |label: &str, readings: &[String]| {
+        Text::new(format!("{}: {}", label, readings.join(", "))).size(18)
+    };
+    Row::new()
+        .spacing(20)
+        .align_items(Alignment::Center)
+        .push(
+            Button::new(Text::new(entry.literal.to_string()).size(50))
+                .padding(5)
+                .style(theme::Button::Secondary)
+                .on_press(Message::KanjiPressed(entry.literal)),
+        )
+        .push(
+            Column::new()
+                .spacing(2)
+                .push(Text::new(entry.meanings.join(", ")).size(22))
+                .push(readings("On", &entry.on_readings))
+                .push(readings("Kun", &entry.kun_readings))
+                .push(
+                    Text::new(entry.summary())
+                        .size(16)
+                        .style(Color::new(0.67, 0.61, 0.60, 1.0)),
+                ),
+        )
+}
+
+// Renders each segment as a little column with its reading on top of the text.
+fn furigana_row<'a>(segments: Vec<FuriganaSegment>, size: u16) -> Row<'a, Message> {
+    segments
+        .into_iter()
+        .fold(Row::new().align_items(Alignment::End), |row, segment| {
+            row.push(
+                Column::new()
+                    .align_items(Alignment::Center)
+                    .push(Text::new(segment.reading.unwrap_or_default()).size(size / 2))
+                    .push(Text::new(segment.text).size(size)),
+            )
+        })
 }
 
 // mod style {
@@ -0,0 +1,125 @@
+// Back/forward history of screens, like a web browser's.
+
+pub trait Page {
+    // Transient pages (e.g. a loading screen) are never returned to.
+    fn is_transient(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug)]
+pub struct Navigation<T> {
+    back: Vec<T>,
+    forward: Vec<T>,
+}
+
+impl<T> Default for Navigation<T> {
+    fn default() -> Self {
+        Navigation {
+            back: Vec::new(),
+            forward: Vec::new(),
+        }
+    }
+}
+
+impl<T: Page> Navigation<T> {
+    // Shows `next`, going somewhere new forgets the pages we went back from.
+    pub fn go_to(&mut self, current: &mut T, next: T) {
+        let previous = std::mem::replace(current, next);
+        if !previous.is_transient() {
+            self.back.push(previous);
+        }
+        self.forward.clear();
+    }
+
+    // Returns to the previous page, or to `home` when there is none.
+    pub fn go_back(&mut self, current: &mut T, home: impl FnOnce() -> T) {
+        let previous = self.back.pop().unwrap_or_else(home);
+        let left = std::mem::replace(current, previous);
+        if !left.is_transient() {
+            self.forward.push(left);
+        }
+    }
+
+    pub fn go_forward(&mut self, current: &mut T) -> bool {
+        match self.forward.pop() {
+            Some(next) => {
+                let left = std::mem::replace(current, next);
+                if !left.is_transient() {
+                    self.back.push(left);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum TestPage {
+        Home,
+        Loading,
+        Page(u32),
+    }
+
+    impl Page for TestPage {
+        fn is_transient(&self) -> bool {
+            *self == TestPage::Loading
+        }
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut navigation = Navigation::default();
+        let mut current = TestPage::Home;
+        navigation.go_to(&mut current, TestPage::Page(1));
+        navigation.go_to(&mut current, TestPage::Page(2));
+
+        navigation.go_back(&mut current, || TestPage::Home);
+        assert_eq!(current, TestPage::Page(1));
+        navigation.go_back(&mut current, || TestPage::Home);
+        assert_eq!(current, TestPage::Home);
+        assert!(!navigation.can_go_back());
+
+        assert!(navigation.go_forward(&mut current));
+        assert!(navigation.go_forward(&mut current));
+        assert_eq!(current, TestPage::Page(2));
+        assert!(!navigation.go_forward(&mut current));
+    }
+
+    #[test]
+    fn test_going_somewhere_new_clears_forward() {
+        let mut navigation = Navigation::default();
+        let mut current = TestPage::Home;
+        navigation.go_to(&mut current, TestPage::Page(1));
+        navigation.go_back(&mut current, || TestPage::Home);
+        navigation.go_to(&mut current, TestPage::Page(2));
+        assert!(!navigation.go_forward(&mut current));
+        assert_eq!(current, TestPage::Page(2));
+    }
+
+    #[test]
+    fn test_transient_pages_are_skipped() {
+        let mut navigation = Navigation::default();
+        let mut current = TestPage::Home;
+        navigation.go_to(&mut current, TestPage::Loading);
+        // the search finished, the results replace the loading page
+        current = TestPage::Page(1);
+        navigation.go_back(&mut current, || TestPage::Home);
+        assert_eq!(current, TestPage::Home);
+
+        navigation.go_to(&mut current, TestPage::Loading);
+        // cancelled
+        navigation.go_back(&mut current, || TestPage::Home);
+        assert_eq!(current, TestPage::Home);
+        assert!(!navigation.go_forward(&mut current));
+    }
+}
@@ -0,0 +1,205 @@
+// Finding kanji by their parts, with the RADKFILE and KRADFILE of the EDRDG:
+// http://www.edrdg.org/krad/kradinf.html
+// Both files are EUC-JP encoded.
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+pub const RADKFILE: &str = "resources/radkfile";
+pub const KRADFILE: &str = "resources/kradfile";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Radical {
+    pub radical: char,
+    pub stroke_count: u32,
+    // every kanji that contains this radical
+    pub kanji: HashSet<char>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Radicals {
+    pub radicals: Vec<Radical>,
+    // the radicals each kanji is made of
+    components: HashMap<char, HashSet<char>>,
+}
+
+impl Radicals {
+    // Without a KRADFILE the components are worked out from the RADKFILE.
+    pub fn new(radicals: Vec<Radical>, components: Option<HashMap<char, HashSet<char>>>) -> Self {
+        let components = components.unwrap_or_else(|| {
+            let mut components: HashMap<char, HashSet<char>> = HashMap::new();
+            for radical in &radicals {
+                for kanji in &radical.kanji {
+                    components
+                        .entry(*kanji)
+                        .or_default()
+                        .insert(radical.radical);
+                }
+            }
+            components
+        });
+        Radicals {
+            radicals,
+            components,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.radicals.is_empty()
+    }
+
+    // The kanji that have all of the selected radicals, nothing while none are selected.
+    pub fn matching_kanji(&self, selected: &[char]) -> Vec<char> {
+        let mut sets = selected.iter().filter_map(|selected| {
+            self.radicals
+                .iter()
+                .find(|radical| radical.radical == *selected)
+                .map(|radical| &radical.kanji)
+        });
+        let mut kanji: Vec<char> = match sets.next() {
+            Some(first) => first
+                .iter()
+                .filter(|kanji| sets.clone().all(|set| set.contains(kanji)))
+                .copied()
+                .collect(),
+            None => Vec::new(),
+        };
+        kanji.sort_unstable();
+        kanji
+    }
+
+    // The radicals that can still be added to the selection without running out of kanji.
+    pub fn available_radicals(&self, selected: &[char]) -> HashSet<char> {
+        if selected.is_empty() {
+            return self
+                .radicals
+                .iter()
+                .map(|radical| radical.radical)
+                .collect();
+        }
+        self.matching_kanji(selected)
+            .iter()
+            .filter_map(|kanji| self.components.get(kanji))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    // The radicals by stroke count, in the order of the RADKFILE.
+    pub fn by_stroke_count(&self) -> BTreeMap<u32, Vec<char>> {
+        let mut groups: BTreeMap<u32, Vec<char>> = BTreeMap::new();
+        for radical in &self.radicals {
+            groups
+                .entry(radical.stroke_count)
+                .or_default()
+                .push(radical.radical);
+        }
+        groups
+    }
+}
+
+pub fn decode_euc_jp(bytes: &[u8]) -> String {
+    let (text, _, _) = encoding_rs::EUC_JP.decode(bytes);
+    text.into_owned()
+}
+
+// A radical starts with "$ <radical> <strokes>", followed by lines of the kanji that use it.
+pub fn parse_radkfile(text: &str) -> Vec<Radical> {
+    let mut radicals: Vec<Radical> = Vec::new();
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        if let Some(header) = line.strip_prefix('$') {
+            let mut fields = header.split_whitespace();
+            let radical = fields.next().and_then(|field| field.chars().next());
+            let stroke_count = fields.next().and_then(|field| field.parse().ok());
+            if let (Some(radical), Some(stroke_count)) = (radical, stroke_count) {
+                radicals.push(Radical {
+                    radical,
+                    stroke_count,
+                    kanji: HashSet::new(),
+                });
+            }
+        } else if let Some(radical) = radicals.last_mut() {
+            radical
+                .kanji
+                .extend(line.chars().filter(|c| !c.is_whitespace()));
+        }
+    }
+    radicals
+}
+
+// Lines like "亜 : ｜ 一 口".
+pub fn parse_kradfile(text: &str) -> HashMap<char, HashSet<char>> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (kanji, components) = line.split_once(':')?;
+            let kanji = kanji.trim().chars().next()?;
+            let components = components
+                .split_whitespace()
+                .filter_map(|component| component.chars().next())
+                .collect();
+            Some((kanji, components))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RADKFILE_SAMPLE: &str = "# comment
+$ 一 1
+一丁七
+三上右
+$ 口 3
+口右
+$ 化 2 js01
+化休
+$ 木 4
+休本
+";
+
+    fn radicals() -> Radicals {
+        Radicals::new(parse_radkfile(RADKFILE_SAMPLE), None)
+    }
+
+    #[test]
+    fn test_parse_radkfile() {
+        let radicals = parse_radkfile(RADKFILE_SAMPLE);
+        assert_eq!(radicals.len(), 4);
+        assert_eq!(radicals[0].radical, '一');
+        assert_eq!(radicals[0].stroke_count, 1);
+        assert_eq!(radicals[0].kanji, "一丁七三上右".chars().collect());
+        assert_eq!(radicals[2].radical, '化');
+        assert_eq!(radicals[2].stroke_count, 2);
+    }
+
+    #[test]
+    fn test_parse_kradfile() {
+        let components = parse_kradfile("# comment\n亜 : ｜ 一 口\n右 : 一 口\n");
+        assert_eq!(components[&'右'], "一口".chars().collect());
+        assert_eq!(components.len(), 2);
+    }
+
+    #[test]
+    fn test_decode_euc_jp() {
+        assert_eq!(decode_euc_jp(&[0xb0, 0xec, 0x20, 0x31]), "一 1");
+    }
+
+    #[test]
+    fn test_matching_kanji() {
+        let radicals = radicals();
+        assert_eq!(radicals.matching_kanji(&[]), Vec::<char>::new());
+        assert_eq!(radicals.matching_kanji(&['化', '木']), vec!['休']);
+        assert_eq!(radicals.matching_kanji(&['口', '木']), Vec::<char>::new());
+    }
+
+    #[test]
+    fn test_available_radicals() {
+        let radicals = radicals();
+        assert_eq!(radicals.available_radicals(&[]).len(), 4);
+        assert_eq!(
+            radicals.available_radicals(&['口']),
+            "口一".chars().collect()
+        );
+        assert_eq!(radicals.by_stroke_count()[&1], vec!['一']);
+    }
+}
@@ -0,0 +1,192 @@
+// How common the words in the search results are, to sort and filter them by.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Ranking {
+    // jisho's "common word" mark
+    pub is_common: bool,
+    // the N level, 5 is the easiest
+    pub jlpt: Option<u8>,
+    // how many example sentences use the word
+    pub frequency: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResultsSort {
+    // the order jisho returned them in
+    #[default]
+    Relevance,
+    Common,
+    Jlpt,
+    Frequency,
+}
+
+// Words with at most this JLPT level, e.g. N3 keeps N5, N4 and N3 words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JlptFilter {
+    #[default]
+    Any,
+    N5,
+    N4,
+    N3,
+    N2,
+    N1,
+}
+
+impl JlptFilter {
+    pub const ALL: [JlptFilter; 6] = [
+        JlptFilter::Any,
+        JlptFilter::N5,
+        JlptFilter::N4,
+        JlptFilter::N3,
+        JlptFilter::N2,
+        JlptFilter::N1,
+    ];
+
+    pub fn allows(&self, jlpt: Option<u8>) -> bool {
+        let hardest = match self {
+            JlptFilter::Any => return true,
+            JlptFilter::N5 => 5,
+            JlptFilter::N4 => 4,
+            JlptFilter::N3 => 3,
+            JlptFilter::N2 => 2,
+            JlptFilter::N1 => 1,
+        };
+        jlpt.is_some_and(|level| level >= hardest)
+    }
+}
+
+impl std::fmt::Display for JlptFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            JlptFilter::Any => "Any JLPT level",
+            JlptFilter::N5 => "JLPT N5",
+            JlptFilter::N4 => "JLPT ≤ N4",
+            JlptFilter::N3 => "JLPT ≤ N3",
+            JlptFilter::N2 => "JLPT ≤ N2",
+            JlptFilter::N1 => "JLPT ≤ N1",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+// Jisho tags levels as "jlpt-n3", a word can have more than one, the easiest counts.
+pub fn jlpt_level(tags: &[String]) -> Option<u8> {
+    tags.iter()
+        .filter_map(|tag| tag.strip_prefix("jlpt-n")?.parse().ok())
+        .max()
+}
+
+// How the search results are shown, kept from one search to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResultsOrder {
+    pub sort: ResultsSort,
+    pub reversed: bool,
+    pub common_only: bool,
+    pub jlpt: JlptFilter,
+}
+
+impl ResultsOrder {
+    // Clicking the column that's already sorted on reverses it.
+    pub fn sort_by(&mut self, sort: ResultsSort) {
+        self.reversed = sort == self.sort && !self.reversed;
+        self.sort = sort;
+    }
+
+    // Indices of the rankings that pass the filters, in the requested order. The
+    // sort is stable, so ties keep jisho's order.
+    pub fn filter_and_sort(&self, rankings: &[Ranking]) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..rankings.len())
+            .filter(|&i| {
+                (!self.common_only || rankings[i].is_common) && self.jlpt.allows(rankings[i].jlpt)
+            })
+            .collect();
+        match self.sort {
+            ResultsSort::Relevance => {}
+            // common words and easy levels first, unknown levels last
+            ResultsSort::Common => indices.sort_by_key(|&i| !rankings[i].is_common),
+            ResultsSort::Jlpt => {
+                indices.sort_by_key(|&i| std::cmp::Reverse(rankings[i].jlpt.unwrap_or_default()))
+            }
+            ResultsSort::Frequency => {
+                indices.sort_by_key(|&i| std::cmp::Reverse(rankings[i].frequency))
+            }
+        }
+        if self.reversed {
+            indices.reverse();
+        }
+        indices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rankings() -> Vec<Ranking> {
+        vec![
+            Ranking {
+                is_common: false,
+                jlpt: None,
+                frequency: 3,
+            },
+            Ranking {
+                is_common: true,
+                jlpt: Some(3),
+                frequency: 40,
+            },
+            Ranking {
+                is_common: true,
+                jlpt: Some(5),
+                frequency: 12,
+            },
+            Ranking {
+                is_common: false,
+                jlpt: Some(1),
+                frequency: 0,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_jlpt_level() {
+        assert_eq!(jlpt_level(&["jlpt-n3".to_string()]), Some(3));
+        assert_eq!(
+            jlpt_level(&["jlpt-n1".to_string(), "jlpt-n4".to_string()]),
+            Some(4)
+        );
+        assert_eq!(jlpt_level(&["wanikani12".to_string()]), None);
+    }
+
+    #[test]
+    fn test_sort() {
+        let rankings = rankings();
+        let mut order = ResultsOrder::default();
+        assert_eq!(order.filter_and_sort(&rankings), vec![0, 1, 2, 3]);
+        order.sort_by(ResultsSort::Common);
+        assert_eq!(order.filter_and_sort(&rankings), vec![1, 2, 0, 3]);
+        order.sort_by(ResultsSort::Jlpt);
+        assert_eq!(order.filter_and_sort(&rankings), vec![2, 1, 3, 0]);
+        order.sort_by(ResultsSort::Frequency);
+        assert_eq!(order.filter_and_sort(&rankings), vec![1, 2, 0, 3]);
+        order.sort_by(ResultsSort::Frequency);
+        assert!(order.reversed);
+        assert_eq!(order.filter_and_sort(&rankings), vec![3, 0, 2, 1]);
+    }
+
+    #[test]
+    fn test_filter() {
+        let rankings = rankings();
+        let filtered = |common_only, jlpt| {
+            ResultsOrder {
+                common_only,
+                jlpt,
+                ..ResultsOrder::default()
+            }
+            .filter_and_sort(&rankings)
+        };
+        assert_eq!(filtered(true, JlptFilter::Any), vec![1, 2]);
+        assert_eq!(filtered(false, JlptFilter::N3), vec![1, 2]);
+        assert_eq!(filtered(false, JlptFilter::N5), vec![2]);
+        assert_eq!(filtered(false, JlptFilter::N1), vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,448 @@
+// Converting between romaji and kana, so a search can be typed without a Japanese IME.
+// Both Hepburn (shi, chi, tsu, ja) and Kunrei (si, ti, tu, zya) spellings are understood.
+use crate::furigana::katakana_to_hiragana;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputMode {
+    // type whatever you want, romaji is sent to jisho as it is
+    #[default]
+    Direct,
+    Hiragana,
+    Katakana,
+}
+
+impl InputMode {
+    pub const ALL: [InputMode; 3] = [InputMode::Direct, InputMode::Hiragana, InputMode::Katakana];
+}
+
+impl std::fmt::Display for InputMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            InputMode::Direct => "Direct input",
+            InputMode::Hiragana => "Romaji to ひらがな",
+            InputMode::Katakana => "Romaji to カタカナ",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomajiSystem {
+    Hepburn,
+    Kunrei,
+}
+
+// How readings are shown next to the kana.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RomajiDisplay {
+    #[default]
+    Off,
+    Hepburn,
+    Kunrei,
+}
+
+impl RomajiDisplay {
+    pub const ALL: [RomajiDisplay; 3] = [
+        RomajiDisplay::Off,
+        RomajiDisplay::Hepburn,
+        RomajiDisplay::Kunrei,
+    ];
+
+    pub fn system(&self) -> Option<RomajiSystem> {
+        match self {
+            RomajiDisplay::Off => None,
+            RomajiDisplay::Hepburn => Some(RomajiSystem::Hepburn),
+            RomajiDisplay::Kunrei => Some(RomajiSystem::Kunrei),
+        }
+    }
+}
+
+impl std::fmt::Display for RomajiDisplay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            RomajiDisplay::Off => "No romaji",
+            RomajiDisplay::Hepburn => "Hepburn romaji",
+            RomajiDisplay::Kunrei => "Kunrei romaji",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+// Where a kana has more than one spelling, Hepburn's comes first.
+#[rustfmt::skip]
+const ROMAJI_TO_HIRAGANA: [(&str, &str); 159] = [
+    ("a", "あ"), ("i", "い"), ("u", "う"), ("e", "え"), ("o", "お"),
+    ("ka", "か"), ("ki", "き"), ("ku", "く"), ("ke", "け"), ("ko", "こ"),
+    ("kya", "きゃ"), ("kyu", "きゅ"), ("kyo", "きょ"),
+    ("ga", "が"), ("gi", "ぎ"), ("gu", "ぐ"), ("ge", "げ"), ("go", "ご"),
+    ("gya", "ぎゃ"), ("gyu", "ぎゅ"), ("gyo", "ぎょ"),
+    ("sa", "さ"), ("shi", "し"), ("si", "し"), ("su", "す"), ("se", "せ"), ("so", "そ"),
+    ("sha", "しゃ"), ("shu", "しゅ"), ("sho", "しょ"), ("she", "しぇ"),
+    ("sya", "しゃ"), ("syu", "しゅ"), ("syo", "しょ"),
+    ("za", "ざ"), ("ji", "じ"), ("zi", "じ"), ("zu", "ず"), ("ze", "ぜ"), ("zo", "ぞ"),
+    ("ja", "じゃ"), ("ju", "じゅ"), ("jo", "じょ"), ("je", "じぇ"),
+    ("zya", "じゃ"), ("zyu", "じゅ"), ("zyo", "じょ"),
+    ("jya", "じゃ"), ("jyu", "じゅ"), ("jyo", "じょ"),
+    ("ta", "た"), ("chi", "ち"), ("ti", "ち"), ("tsu", "つ"), ("tu", "つ"), ("te", "て"), ("to", "と"),
+    ("cha", "ちゃ"), ("chu", "ちゅ"), ("cho", "ちょ"), ("che", "ちぇ"),
+    ("tya", "ちゃ"), ("tyu", "ちゅ"), ("tyo", "ちょ"),
+    ("da", "だ"), ("di", "ぢ"), ("du", "づ"), ("de", "で"), ("do", "ど"),
+    ("dya", "ぢゃ"), ("dyu", "ぢゅ"), ("dyo", "ぢょ"),
+    ("na", "な"), ("ni", "に"), ("nu", "ぬ"), ("ne", "ね"), ("no", "の"),
+    ("nya", "にゃ"), ("nyu", "にゅ"), ("nyo", "にょ"),
+    ("ha", "は"), ("hi", "ひ"), ("fu", "ふ"), ("hu", "ふ"), ("he", "へ"), ("ho", "ほ"),
+    ("hya", "ひゃ"), ("hyu", "ひゅ"), ("hyo", "ひょ"),
+    ("fa", "ふぁ"), ("fi", "ふぃ"), ("fe", "ふぇ"), ("fo", "ふぉ"),
+    ("ba", "ば"), ("bi", "び"), ("bu", "ぶ"), ("be", "べ"), ("bo", "ぼ"),
+    ("bya", "びゃ"), ("byu", "びゅ"), ("byo", "びょ"),
+    ("pa", "ぱ"), ("pi", "ぴ"), ("pu", "ぷ"), ("pe", "ぺ"), ("po", "ぽ"),
+    ("pya", "ぴゃ"), ("pyu", "ぴゅ"), ("pyo", "ぴょ"),
+    ("ma", "ま"), ("mi", "み"), ("mu", "む"), ("me", "め"), ("mo", "も"),
+    ("mya", "みゃ"), ("myu", "みゅ"), ("myo", "みょ"),
+    ("ya", "や"), ("yu", "ゆ"), ("yo", "よ"),
+    ("ra", "ら"), ("ri", "り"), ("ru", "る"), ("re", "れ"), ("ro", "ろ"),
+    ("rya", "りゃ"), ("ryu", "りゅ"), ("ryo", "りょ"),
+    ("wa", "わ"), ("wo", "を"), ("wi", "うぃ"), ("we", "うぇ"),
+    ("va", "ゔぁ"), ("vi", "ゔぃ"), ("vu", "ゔ"), ("ve", "ゔぇ"), ("vo", "ゔぉ"),
+    ("xa", "ぁ"), ("xi", "ぃ"), ("xu", "ぅ"), ("xe", "ぇ"), ("xo", "ぉ"),
+    ("la", "ぁ"), ("li", "ぃ"), ("lu", "ぅ"), ("le", "ぇ"), ("lo", "ぉ"),
+    ("xya", "ゃ"), ("xyu", "ゅ"), ("xyo", "ょ"),
+    ("lya", "ゃ"), ("lyu", "ゅ"), ("lyo", "ょ"),
+    ("xtsu", "っ"), ("xtu", "っ"), ("ltsu", "っ"), ("ltu", "っ"), ("xwa", "ゎ"),
+    ("-", "ー"),
+];
+
+// The spellings that differ from the first one in the table above.
+const HEPBURN: [(&str, &str); 5] = [
+    ("ぢ", "ji"),
+    ("づ", "zu"),
+    ("を", "o"),
+    ("ぢゃ", "ja"),
+    ("ぢょ", "jo"),
+];
+#[rustfmt::skip]
+const KUNREI: [(&str, &str); 21] = [
+    ("し", "si"), ("ち", "ti"), ("つ", "tu"), ("ふ", "hu"), ("じ", "zi"), ("ぢ", "zi"),
+    ("づ", "zu"), ("を", "o"),
+    ("しゃ", "sya"), ("しゅ", "syu"), ("しょ", "syo"),
+    ("ちゃ", "tya"), ("ちゅ", "tyu"), ("ちょ", "tyo"),
+    ("じゃ", "zya"), ("じゅ", "zyu"), ("じょ", "zyo"),
+    ("ぢゃ", "zya"), ("ぢゅ", "zyu"), ("ぢょ", "zyo"),
+    ("ゔ", "vu"),
+];
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'i' | 'u' | 'e' | 'o')
+}
+
+// ā or â to a, for long vowels.
+fn plain_vowel(c: char) -> Option<char> {
+    match c {
+        'ā' | 'â' => Some('a'),
+        'ī' | 'î' => Some('i'),
+        'ū' | 'û' => Some('u'),
+        'ē' | 'ê' => Some('e'),
+        'ō' | 'ô' => Some('o'),
+        _ => None,
+    }
+}
+
+fn long_vowel(c: char, system: RomajiSystem) -> char {
+    match (c, system) {
+        ('a', RomajiSystem::Hepburn) => 'ā',
+        ('i', RomajiSystem::Hepburn) => 'ī',
+        ('u', RomajiSystem::Hepburn) => 'ū',
+        ('e', RomajiSystem::Hepburn) => 'ē',
+        ('o', RomajiSystem::Hepburn) => 'ō',
+        ('a', RomajiSystem::Kunrei) => 'â',
+        ('i', RomajiSystem::Kunrei) => 'î',
+        ('u', RomajiSystem::Kunrei) => 'û',
+        ('e', RomajiSystem::Kunrei) => 'ê',
+        ('o', RomajiSystem::Kunrei) => 'ô',
+        _ => c,
+    }
+}
+
+pub fn hiragana_to_katakana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{3041}'..='\u{3096}' => char::from_u32(c as u32 + 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+// Converts the romaji in `input` to kana, anything else is kept as it is. While the
+// user is still typing (`finished` is false) a trailing n or nn stays, it may become な
+// or んな, so converting on every key press ends up the same as converting at once.
+pub fn to_kana(input: &str, mode: InputMode, finished: bool) -> String {
+    if mode == InputMode::Direct {
+        return input.to_string();
+    }
+    let katakana = mode == InputMode::Katakana;
+    // long vowels are spelled out: ō is "ou" for おう and "o-" for オー
+    let chars: Vec<char> = input
+        .chars()
+        .flat_map(char::to_lowercase)
+        .flat_map(|c| match plain_vowel(c) {
+            Some(vowel) if katakana => vec![vowel, '-'],
+            Some('o') => vec!['o', 'u'],
+            Some(vowel) => vec![vowel, vowel],
+            None => vec![c],
+        })
+        .collect();
+    let mut hiragana = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c == 'n' {
+            match next {
+                // "nn" is ん, unless the second n starts the next syllable as in "onna"
+                Some('n') => {
+                    let after = chars.get(i + 2).copied();
+                    if after.is_none() && !finished {
+                        hiragana.push_str("nn");
+                        i += 2;
+                        continue;
+                    }
+                    hiragana.push('ん');
+                    i += if after.is_some_and(|a| is_vowel(a) || a == 'y') {
+                        1
+                    } else {
+                        2
+                    };
+                    continue;
+                }
+                Some('\'') => {
+                    hiragana.push('ん');
+                    i += 2;
+                    continue;
+                }
+                Some(next) if is_vowel(next) || next == 'y' => {}
+                Some(_) => {
+                    hiragana.push('ん');
+                    i += 1;
+                    continue;
+                }
+                None => {
+                    hiragana.push(if finished { 'ん' } else { 'n' });
+                    i += 1;
+                    continue;
+                }
+            }
+        }
+
+        // a doubled consonant is a small tsu, so is the t of "tch"
+        if c.is_ascii_alphabetic()
+            && !is_vowel(c)
+            && (next == Some(c) || (c == 't' && next == Some('c')))
+        {
+            hiragana.push('っ');
+            i += 1;
+            continue;
+        }
+
+        let found = (1..=4).rev().find_map(|length| {
+            let romaji: String = chars.get(i..i + length)?.iter().collect();
+            ROMAJI_TO_HIRAGANA
+                .iter()
+                .find(|(r, _)| *r == romaji)
+                .map(|(_, kana)| (length, *kana))
+        });
+        match found {
+            Some((length, kana)) => {
+                hiragana.push_str(kana);
+                i += length;
+            }
+            // not (yet) a syllable
+            None => {
+                hiragana.push(c);
+                i += 1;
+            }
+        }
+    }
+    if katakana {
+        hiragana_to_katakana(&hiragana)
+    } else {
+        hiragana
+    }
+}
+
+fn syllable_romaji(kana: &str, system: RomajiSystem) -> Option<&'static str> {
+    let overrides: &[(&str, &str)] = match system {
+        RomajiSystem::Hepburn => &HEPBURN,
+        RomajiSystem::Kunrei => &KUNREI,
+    };
+    overrides
+        .iter()
+        .find(|(k, _)| *k == kana)
+        .map(|(_, romaji)| *romaji)
+        .or_else(|| {
+            ROMAJI_TO_HIRAGANA
+                .iter()
+                .find(|(_, k)| *k == kana)
+                .map(|(romaji, _)| *romaji)
+        })
+}
+
+// Romanizes a reading, e.g. とうきょう is tōkyō in Hepburn and tôkyô in Kunrei.
+pub fn to_romaji(kana: &str, system: RomajiSystem) -> String {
+    let chars: Vec<char> = katakana_to_hiragana(kana).chars().collect();
+    let mut romaji = String::new();
+    let mut small_tsu = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == 'っ' {
+            small_tsu = true;
+            i += 1;
+            continue;
+        }
+        if c == 'ん' {
+            romaji.push('n');
+            // kon'ya, not konya (こにゃ)
+            if matches!(
+                chars.get(i + 1),
+                Some('あ' | 'い' | 'う' | 'え' | 'お' | 'や' | 'ゆ' | 'よ')
+            ) {
+                romaji.push('\'');
+            }
+            i += 1;
+            continue;
+        }
+        if c == 'ー' {
+            if let Some(last) = romaji.pop() {
+                romaji.push(long_vowel(last, system));
+            }
+            i += 1;
+            continue;
+        }
+
+        let found = (1..=2).rev().find_map(|length| {
+            let kana: String = chars.get(i..i + length)?.iter().collect();
+            syllable_romaji(&kana, system).map(|romaji| (length, romaji))
+        });
+        match found {
+            Some((length, syllable)) => {
+                if small_tsu {
+                    romaji.push(match syllable {
+                        s if s.starts_with("ch") => 't',
+                        s => s.chars().next().unwrap_or_default(),
+                    });
+                    small_tsu = false;
+                }
+                let last = romaji.chars().last();
+                match (last, syllable) {
+                    // おう, おお, うう and ああ are long vowels
+                    (Some('o'), "u") | (Some('o'), "o") | (Some('u'), "u") | (Some('a'), "a") => {
+                        let last = romaji.pop().unwrap_or_default();
+                        romaji.push(long_vowel(last, system));
+                    }
+                    _ => romaji.push_str(syllable),
+                }
+                i += length;
+            }
+            None => {
+                romaji.push(c);
+                i += 1;
+            }
+        }
+    }
+    romaji
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hiragana(input: &str) -> String {
+        to_kana(input, InputMode::Hiragana, true)
+    }
+
+    #[test]
+    fn test_to_hiragana() {
+        assert_eq!(hiragana("konnichiwa"), "こんにちわ");
+        assert_eq!(hiragana("konnnichiha"), "こんにちは");
+        assert_eq!(hiragana("onna"), "おんな");
+        assert_eq!(hiragana("kanji"), "かんじ");
+        assert_eq!(hiragana("kan'i"), "かんい");
+        assert_eq!(hiragana("kon'ya"), "こんや");
+        assert_eq!(hiragana("shinbun"), "しんぶん");
+        assert_eq!(hiragana("sinbun"), "しんぶん");
+        assert_eq!(hiragana("tyotto"), "ちょっと");
+        assert_eq!(hiragana("chotto"), "ちょっと");
+        assert_eq!(hiragana("matcha"), "まっちゃ");
+        assert_eq!(hiragana("gakkou"), "がっこう");
+        assert_eq!(hiragana("Tōkyō"), "とうきょう");
+        assert_eq!(hiragana("zyûdô"), "じゅうどう");
+        assert_eq!(hiragana("猫neko"), "猫ねこ");
+    }
+
+    #[test]
+    fn test_to_katakana() {
+        assert_eq!(to_kana("ra-men", InputMode::Katakana, true), "ラーメン");
+        assert_eq!(to_kana("tōkyō", InputMode::Katakana, true), "トーキョー");
+        assert_eq!(
+            to_kana("konpyu-ta-", InputMode::Katakana, true),
+            "コンピューター"
+        );
+    }
+
+    #[test]
+    fn test_to_kana_while_typing() {
+        assert_eq!(to_kana("kan", InputMode::Hiragana, false), "かn");
+        assert_eq!(to_kana("onn", InputMode::Hiragana, false), "おnn");
+        assert_eq!(to_kana("kany", InputMode::Hiragana, false), "かny");
+        assert_eq!(to_kana("かny", InputMode::Hiragana, false), "かny");
+        assert_eq!(to_kana("かnya", InputMode::Hiragana, false), "かにゃ");
+        assert_eq!(to_kana("sh", InputMode::Hiragana, false), "sh");
+        assert_eq!(to_kana("cat", InputMode::Direct, true), "cat");
+    }
+
+    // The search box converts what's in it on every key press, like InputChanged does.
+    fn typed(romaji: &str) -> String {
+        let mut value = String::new();
+        for c in romaji.chars() {
+            value.push(c);
+            value = to_kana(&value, InputMode::Hiragana, false);
+        }
+        to_kana(&value, InputMode::Hiragana, true)
+    }
+
+    #[test]
+    fn test_typing_matches_converting_at_once() {
+        for romaji in [
+            "konnichiwa",
+            "konnnichiha",
+            "onna",
+            "sannpo",
+            "kan'i",
+            "kon'ya",
+            "kanji",
+            "matcha",
+            "gakkou",
+        ] {
+            assert_eq!(typed(romaji), hiragana(romaji), "{}", romaji);
+        }
+    }
+
+    #[test]
+    fn test_to_romaji() {
+        assert_eq!(to_romaji("とうきょう", RomajiSystem::Hepburn), "tōkyō");
+        assert_eq!(to_romaji("とうきょう", RomajiSystem::Kunrei), "tôkyô");
+        assert_eq!(to_romaji("しんぶん", RomajiSystem::Hepburn), "shinbun");
+        assert_eq!(to_romaji("しんぶん", RomajiSystem::Kunrei), "sinbun");
+        assert_eq!(to_romaji("こんや", RomajiSystem::Hepburn), "kon'ya");
+        assert_eq!(to_romaji("ちょっと", RomajiSystem::Hepburn), "chotto");
+        assert_eq!(to_romaji("ちょっと", RomajiSystem::Kunrei), "tyotto");
+        assert_eq!(to_romaji("まっちゃ", RomajiSystem::Hepburn), "matcha");
+        assert_eq!(to_romaji("まっちゃ", RomajiSystem::Kunrei), "mattya");
+        assert_eq!(to_romaji("ラーメン", RomajiSystem::Hepburn), "rāmen");
+        assert_eq!(to_romaji("ふじさん", RomajiSystem::Kunrei), "huzisan");
+    }
+}
@@ -0,0 +1,532 @@
+// Full-text search over the example sentences, in both directions: English words
+// are stemmed and ranked with BM25, Japanese text is found through its n-grams.
+//
+// Japanese queries are one or more terms separated by spaces that all have to be
+// in the sentence. In a term * (or 〜) stands for any text and ? for one character,
+// so ばかり*か finds 〜ばかりか〜 patterns. Quote a term ("..." or 「...」) to
+// search for it exactly, spaces and all.
+use crate::example_sentences::ExampleSentence;
+use crate::furigana::is_kanji;
+use std::collections::HashMap;
+use std::ops::Range;
+
+// BM25 parameters, the usual defaults
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentenceMatch {
+    // position in `SentenceIndex::sentences`
+    pub id: usize,
+    pub score: f32,
+    // byte ranges of the matched words, in the text that was searched
+    pub highlights: Vec<Range<usize>>,
+    pub english: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct SentenceIndex {
+    sentences: Vec<ExampleSentence>,
+    // stem -> (sentence, how often the stem is in it)
+    english: HashMap<String, Vec<(u32, u16)>>,
+    english_lengths: Vec<u16>,
+    average_english_length: f32,
+    // runs of one to three characters -> sentences containing them, in order
+    japanese: HashMap<u64, Vec<u32>>,
+}
+
+impl SentenceIndex {
+    pub fn new(sentences: Vec<ExampleSentence>) -> Self {
+        let mut english: HashMap<String, Vec<(u32, u16)>> = HashMap::new();
+        let mut english_lengths: Vec<u16> = Vec::with_capacity(sentences.len());
+        let mut japanese: HashMap<u64, Vec<u32>> = HashMap::new();
+
+        for (id, sentence) in sentences.iter().enumerate() {
+            let id = id as u32;
+            let mut counts: HashMap<String, u16> = HashMap::new();
+            let tokens = tokenize(&sentence.english_text);
+            english_lengths.push(tokens.len() as u16);
+            for (_, token) in tokens {
+                *counts.entry(stem(&token)).or_default() += 1;
+            }
+            for (stem, count) in counts {
+                english.entry(stem).or_default().push((id, count));
+            }
+
+            for gram in ngrams(&sentence.japanese_text) {
+                let postings = japanese.entry(gram).or_default();
+                // sentences are added in order, so a repeat is always the last one
+                if postings.last() != Some(&id) {
+                    postings.push(id);
+                }
+            }
+        }
+
+        let average_english_length = if sentences.is_empty() {
+            0.0
+        } else {
+            english_lengths
+                .iter()
+                .map(|length| *length as f32)
+                .sum::<f32>()
+                / sentences.len() as f32
+        };
+        SentenceIndex {
+            sentences,
+            english,
+            english_lengths,
+            average_english_length,
+            japanese,
+        }
+    }
+
+    pub fn sentence(&self, id: usize) -> Option<&ExampleSentence> {
+        self.sentences.get(id)
+    }
+
+    pub fn sentences(&self) -> &[ExampleSentence] {
+        &self.sentences
+    }
+
+    // Japanese queries look in the Japanese text, anything else in the translations.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SentenceMatch> {
+        if query.chars().any(is_japanese) {
+            self.search_japanese(query, limit)
+        } else {
+            self.search_english(query, limit)
+        }
+    }
+
+    // Sentences with any of the query's words, best first.
+    pub fn search_english(&self, query: &str, limit: usize) -> Vec<SentenceMatch> {
+        let mut stems: Vec<String> = tokenize(query)
+            .into_iter()
+            .map(|(_, token)| stem(&token))
+            .collect();
+        stems.sort();
+        stems.dedup();
+
+        let count = self.sentences.len() as f32;
+        let mut scores: HashMap<u32, f32> = HashMap::new();
+        for stem in &stems {
+            let postings = match self.english.get(stem) {
+                Some(postings) => postings,
+                None => continue,
+            };
+            let frequency = postings.len() as f32;
+            let idf = ((count - frequency + 0.5) / (frequency + 0.5) + 1.0).ln();
+            for (id, term_count) in postings {
+                let length = self.english_lengths[*id as usize] as f32;
+                let term_count = *term_count as f32;
+                let score = idf * term_count * (K1 + 1.0)
+                    / (term_count
+                        + K1 * (1.0 - B + B * length / self.average_english_length.max(1.0)));
+                *scores.entry(*id).or_default() += score;
+            }
+        }
+
+        let mut matches: Vec<SentenceMatch> = scores
+            .into_iter()
+            .map(|(id, score)| SentenceMatch {
+                id: id as usize,
+                score,
+                highlights: Vec::new(),
+                english: true,
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        matches.truncate(limit);
+        for found in &mut matches {
+            found.highlights = tokenize(&self.sentences[found.id].english_text)
+                .into_iter()
+                .filter(|(_, token)| stems.contains(&stem(token)))
+                .map(|(range, _)| range)
+                .collect();
+        }
+        matches
+    }
+
+    // Sentences with every term of the query, shortest first.
+    pub fn search_japanese(&self, query: &str, limit: usize) -> Vec<SentenceMatch> {
+        let terms = parse_query(query);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        let mut matches: Vec<SentenceMatch> = self
+            .candidates(&terms)
+            .into_iter()
+            .filter_map(|id| {
+                let text = &self.sentences[id as usize].japanese_text;
+                let chars: Vec<(usize, char)> = text.char_indices().collect();
+                let mut highlights: Vec<Range<usize>> = Vec::new();
+                for term in &terms {
+                    let found = find_all(term, &chars, text.len());
+                    if found.is_empty() {
+                        return None;
+                    }
+                    highlights.extend(found);
+                }
+                highlights.sort_by_key(|range| range.start);
+                Some(SentenceMatch {
+                    id: id as usize,
+                    score: 1.0 / text.chars().count() as f32,
+                    highlights,
+                    english: false,
+                })
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        matches.truncate(limit);
+        matches
+    }
+
+    // The sentences that have every n-gram of the literal text in the terms, they
+    // still have to be matched against the terms themselves. Trigrams are rarer than
+    // pairs, so the shorter lists are intersected first.
+    fn candidates(&self, terms: &[Vec<Token>]) -> Vec<u32> {
+        let mut keys: Vec<u64> = Vec::new();
+        for term in terms {
+            for token in term {
+                if let Token::Literal(literal) = token {
+                    let size = literal.len().min(3);
+                    keys.extend(literal.windows(size).map(gram_key));
+                }
+            }
+        }
+        // only wildcards, there's nothing to look up
+        if keys.is_empty() {
+            return Vec::new();
+        }
+        let mut postings: Vec<&Vec<u32>> = Vec::new();
+        for key in &keys {
+            match self.japanese.get(key) {
+                Some(list) => postings.push(list),
+                None => return Vec::new(),
+            }
+        }
+        postings.sort_by_key(|list| list.len());
+        let mut candidates = postings[0].clone();
+        for list in &postings[1..] {
+            candidates.retain(|id| list.binary_search(id).is_ok());
+            if candidates.is_empty() {
+                break;
+            }
+        }
+        candidates
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(Vec<char>),
+    // * or 〜
+    AnyText,
+    // ?
+    AnyChar,
+}
+
+// Splits a query into its terms. Wildcards at the ends of a term don't change
+// what matches, only how much gets highlighted, so they are left out.
+fn parse_query(query: &str) -> Vec<Vec<Token>> {
+    let mut terms: Vec<Vec<Token>> = Vec::new();
+    let mut term: Vec<Token> = Vec::new();
+    let mut quote: Option<char> = None;
+    let push_char = |term: &mut Vec<Token>, c: char| match term.last_mut() {
+        Some(Token::Literal(literal)) => literal.push(c),
+        _ => term.push(Token::Literal(vec![c])),
+    };
+    let finish = |term: &mut Vec<Token>, terms: &mut Vec<Vec<Token>>| {
+        while matches!(term.first(), Some(Token::AnyText)) {
+            term.remove(0);
+        }
+        while matches!(term.last(), Some(Token::AnyText)) {
+            term.pop();
+        }
+        if !term.is_empty() {
+            terms.push(std::mem::take(term));
+        }
+    };
+    for c in query.chars() {
+        match (quote, c) {
+            (Some(closing), c) if c == closing => {
+                quote = None;
+                finish(&mut term, &mut terms);
+            }
+            (Some(_), c) => push_char(&mut term, c),
+            (None, '"') => quote = Some('"'),
+            (None, '「') => quote = Some('」'),
+            (None, c) if c.is_whitespace() => finish(&mut term, &mut terms),
+            (None, '*' | '＊' | '〜' | '～') => {
+                if term.last() != Some(&Token::AnyText) {
+                    term.push(Token::AnyText);
+                }
+            }
+            (None, '?' | '？') => term.push(Token::AnyChar),
+            (None, c) => push_char(&mut term, c),
+        }
+    }
+    finish(&mut term, &mut terms);
+    terms
+}
+
+// Where the term matches at `position`, wildcards match as little as they can.
+fn match_here(term: &[Token], chars: &[(usize, char)], position: usize) -> Option<usize> {
+    match term.split_first() {
+        None => Some(position),
+        Some((Token::Literal(literal), rest)) => {
+            let end = position + literal.len();
+            let matches = end <= chars.len()
+                && chars[position..end]
+                    .iter()
+                    .zip(literal)
+                    .all(|((_, c), l)| c == l);
+            if matches {
+                match_here(rest, chars, end)
+            } else {
+                None
+            }
+        }
+        Some((Token::AnyChar, rest)) if position < chars.len() => {
+            match_here(rest, chars, position + 1)
+        }
+        Some((Token::AnyChar, _)) => None,
+        Some((Token::AnyText, rest)) => {
+            (position..=chars.len()).find_map(|from| match_here(rest, chars, from))
+        }
+    }
+}
+
+// Byte ranges of every (non-overlapping) match of the term in the text.
+fn find_all(term: &[Token], chars: &[(usize, char)], text_length: usize) -> Vec<Range<usize>> {
+    let byte_offset = |position: usize| {
+        chars
+            .get(position)
+            .map(|(offset, _)| *offset)
+            .unwrap_or(text_length)
+    };
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut position = 0;
+    while position < chars.len() {
+        match match_here(term, chars, position) {
+            Some(end) if end > position => {
+                ranges.push(byte_offset(position)..byte_offset(end));
+                position = end;
+            }
+            _ => position += 1,
+        }
+    }
+    ranges
+}
+
+// Up to three characters packed into one number, 21 bits each.
+fn gram_key(chars: &[char]) -> u64 {
+    chars.iter().fold(0, |key, c| (key << 21) | *c as u64)
+}
+
+pub fn is_japanese(c: char) -> bool {
+    is_kanji(c) || matches!(c, '\u{3040}'..='\u{30ff}' | '\u{ff66}'..='\u{ff9f}')
+}
+
+// Lowercased words with their byte ranges, "don't" is one word.
+pub fn tokenize(text: &str) -> Vec<(Range<usize>, String)> {
+    let mut tokens: Vec<(Range<usize>, String)> = Vec::new();
+    let mut start: Option<usize> = None;
+    for (position, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        let in_word = c.is_alphanumeric() || (c == '\'' && start.is_some());
+        match (in_word, start) {
+            (true, None) => start = Some(position),
+            (false, Some(from)) => {
+                let word = text[from..position].trim_end_matches('\'');
+                tokens.push((from..from + word.len(), word.to_lowercase()));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+// A light suffix stripper in the spirit of Porter's: cats, cat's, running, stopped
+// and likes all end up the same as cat, run, stop and like.
+pub fn stem(word: &str) -> String {
+    let mut word = word.to_lowercase();
+    if let Some(stripped) = word.strip_suffix("'s") {
+        word = stripped.to_string();
+    }
+    let length = word.chars().count();
+    if length > 4 && word.ends_with("ies") {
+        word.truncate(word.len() - 3);
+        word.push('y');
+    } else if word.ends_with("sses") {
+        word.truncate(word.len() - 2);
+    } else if length > 3 && word.ends_with('s') && !word.ends_with("ss") && !word.ends_with("us") {
+        word.pop();
+    }
+
+    let length = word.chars().count();
+    for suffix in ["ing", "ed", "ly"] {
+        if length > suffix.len() + 2 && word.ends_with(suffix) {
+            word.truncate(word.len() - suffix.len());
+            // stopp -> stop, but fall stays fall
+            let mut chars = word.chars().rev();
+            if let (Some(last), Some(before)) = (chars.next(), chars.next()) {
+                if last == before && !matches!(last, 'l' | 's' | 'z') {
+                    word.pop();
+                }
+            }
+            break;
+        }
+    }
+    if word.chars().count() > 3 && word.ends_with('e') {
+        word.pop();
+    }
+    word
+}
+
+// Every run of one, two and three characters.
+fn ngrams(text: &str) -> Vec<u64> {
+    let chars: Vec<char> = text.chars().collect();
+    (1..=3)
+        .flat_map(|size| chars.windows(size).map(gram_key).collect::<Vec<u64>>())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sentence(japanese: &str, english: &str) -> ExampleSentence {
+        ExampleSentence {
+            japanese_text: japanese.to_string(),
+            english_text: english.to_string(),
+            ..ExampleSentence::default()
+        }
+    }
+
+    fn index() -> SentenceIndex {
+        SentenceIndex::new(vec![
+            sentence("猫が好きです。", "I like cats."),
+            sentence("彼は走っている猫を見た。", "He saw a running cat."),
+            sentence("犬が好きですか。", "Do you like dogs?"),
+            sentence(
+                "勉強ばかりか遊びもする。",
+                "Not only studying but also playing.",
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_stem() {
+        assert_eq!(stem("cats"), "cat");
+        assert_eq!(stem("Cat's"), "cat");
+        assert_eq!(stem("running"), "run");
+        assert_eq!(stem("stopped"), "stop");
+        assert_eq!(stem("likes"), stem("liked"));
+        assert_eq!(stem("likes"), stem("like"));
+        assert_eq!(stem("studies"), "study");
+        assert_eq!(stem("glasses"), "glass");
+        assert_eq!(stem("is"), "is");
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let text = "Don't run, Tom!";
+        let tokens = tokenize(text);
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|(_, token)| token.as_str())
+                .collect::<Vec<_>>(),
+            vec!["don't", "run", "tom"]
+        );
+        assert_eq!(&text[tokens[2].0.clone()], "Tom");
+    }
+
+    #[test]
+    fn test_search_english() {
+        let index = index();
+        let matches = index.search("cat", 10);
+        assert_eq!(
+            matches.iter().map(|found| found.id).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        let english = &index.sentence(0).unwrap().english_text;
+        assert_eq!(&english[matches[0].highlights[0].clone()], "cats");
+
+        // the sentence with both words comes first
+        let matches = index.search("like dog", 10);
+        assert_eq!(matches[0].id, 2);
+        assert_eq!(matches.len(), 2);
+        assert!(index.search("elephant", 10).is_empty());
+    }
+
+    #[test]
+    fn test_search_japanese() {
+        let index = index();
+        let matches = index.search("好きです", 10);
+        assert_eq!(
+            matches.iter().map(|found| found.id).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        let japanese = &index.sentence(0).unwrap().japanese_text;
+        assert_eq!(&japanese[matches[0].highlights[0].clone()], "好きです");
+
+        assert_eq!(index.search("猫", 10).len(), 2);
+        assert_eq!(index.search("ばかりか", 10)[0].id, 3);
+        // every pair is there, but not in this order
+        assert!(index.search("好き猫", 10).is_empty());
+    }
+
+    #[test]
+    fn test_search_japanese_terms() {
+        let index = index();
+        // all the terms have to be there
+        let matches = index.search("猫 見た", 10);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].id, 1);
+        assert_eq!(matches[0].highlights.len(), 2);
+        assert!(index.search("猫 犬", 10).is_empty());
+        // quoted, the space is part of what's searched for
+        assert!(index.search("「猫 見た」", 10).is_empty());
+        assert_eq!(index.search("\"好きです\"", 10).len(), 2);
+    }
+
+    #[test]
+    fn test_search_japanese_wildcards() {
+        let index = index();
+        let matches = index.search("ばかり*か", 10);
+        assert_eq!(matches.len(), 1);
+        let japanese = &index.sentence(3).unwrap().japanese_text;
+        assert_eq!(&japanese[matches[0].highlights[0].clone()], "ばかりか");
+        // as little as possible: up to the first か after 勉強
+        let matches = index.search("勉強*か", 10);
+        assert_eq!(&japanese[matches[0].highlights[0].clone()], "勉強ばか");
+        assert_eq!(index.search("〜ばかりか〜", 10).len(), 1);
+
+        let matches = index.search("好き?す", 10);
+        assert_eq!(matches.len(), 2);
+        assert!(index.search("好き?です", 10).is_empty());
+        assert!(index.search("*", 10).is_empty());
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query("*ばかり*か?"),
+            vec![vec![
+                Token::Literal(vec!['ば', 'か', 'り']),
+                Token::AnyText,
+                Token::Literal(vec!['か']),
+                Token::AnyChar,
+            ]]
+        );
+        assert_eq!(parse_query("猫　犬").len(), 2);
+        assert_eq!(
+            parse_query("\"a b\""),
+            vec![vec![Token::Literal(vec!['a', ' ', 'b'])]]
+        );
+    }
+}
@@ -0,0 +1,304 @@
+// Spaced repetition of saved words, scheduled with SM-2:
+// https://super-memory.com/english/ol/sm2.htm
+use rusqlite::{params, Connection, OptionalExtension};
+
+pub const STUDY_DATABASE: &str = "study.sqlite";
+
+const MINUTE: u64 = 60;
+const DAY: u64 = 24 * 60 * MINUTE;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StudyCard {
+    pub word: String,
+    pub reading: String,
+    pub translation: String,
+    pub sentence: String,
+    pub sentence_translation: String,
+    pub ease: f64,
+    pub interval_days: u32,
+    pub repetitions: u32,
+    pub lapses: u32,
+    // unix timestamp in seconds
+    pub due: u64,
+}
+
+impl StudyCard {
+    pub fn new(
+        word: String,
+        reading: String,
+        translation: String,
+        sentence: String,
+        sentence_translation: String,
+        now: u64,
+    ) -> Self {
+        Self {
+            word,
+            reading,
+            translation,
+            sentence,
+            sentence_translation,
+            ease: 2.5,
+            interval_days: 0,
+            repetitions: 0,
+            lapses: 0,
+            due: now,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    pub const ALL: [Grade; 4] = [Grade::Again, Grade::Hard, Grade::Good, Grade::Easy];
+
+    // SM-2 response quality, from 0 (blackout) to 5 (perfect)
+    fn quality(&self) -> u32 {
+        match self {
+            Grade::Again => 1,
+            Grade::Hard => 3,
+            Grade::Good => 4,
+            Grade::Easy => 5,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Grade::Again => "Again",
+            Grade::Hard => "Hard",
+            Grade::Good => "Good",
+            Grade::Easy => "Easy",
+        }
+    }
+}
+
+// A forgotten card starts over without its ease changing, as SM-2 has it. SM-2 repeats
+// it later in the same session, here that's in 10 minutes.
+pub fn schedule(card: &mut StudyCard, grade: Grade, now: u64) {
+    let quality = grade.quality();
+    if quality < 3 {
+        card.repetitions = 0;
+        card.interval_days = 0;
+        card.lapses += 1;
+        card.due = now + 10 * MINUTE;
+        return;
+    }
+    card.interval_days = match card.repetitions {
+        0 => 1,
+        1 => 6,
+        _ => (card.interval_days as f64 * card.ease).round() as u32,
+    };
+    card.repetitions += 1;
+    card.due = now + card.interval_days as u64 * DAY;
+    let penalty = (5 - quality) as f64;
+    card.ease = (card.ease + 0.1 - penalty * (0.08 + penalty * 0.02)).max(1.3);
+}
+
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn open_database() -> rusqlite::Result<Connection> {
+    let connection = Connection::open(STUDY_DATABASE)?;
+    create_schema(&connection)?;
+    Ok(connection)
+}
+
+pub fn create_schema(connection: &Connection) -> rusqlite::Result<()> {
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS cards (
+            word TEXT PRIMARY KEY,
+            reading TEXT NOT NULL,
+            translation TEXT NOT NULL,
+            sentence TEXT NOT NULL,
+            sentence_translation TEXT NOT NULL,
+            ease REAL NOT NULL,
+            interval_days INTEGER NOT NULL,
+            repetitions INTEGER NOT NULL,
+            lapses INTEGER NOT NULL,
+            due INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS reviews (
+            word TEXT NOT NULL,
+            reviewed_at INTEGER NOT NULL,
+            grade TEXT NOT NULL
+        );",
+    )
+}
+
+// Saving a word that is already being studied keeps its schedule.
+pub fn add_card(connection: &Connection, card: &StudyCard) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT OR IGNORE INTO cards VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            card.word,
+            card.reading,
+            card.translation,
+            card.sentence,
+            card.sentence_translation,
+            card.ease,
+            card.interval_days,
+            card.repetitions,
+            card.lapses,
+            card.due,
+        ],
+    )?;
+    Ok(())
+}
+
+// Takes back a word that was just saved, cards with a review history are kept.
+pub fn remove_new_card(connection: &Connection, word: &str) -> rusqlite::Result<()> {
+    connection.execute(
+        "DELETE FROM cards WHERE word = ?1 AND repetitions = 0 AND lapses = 0
+         AND NOT EXISTS (SELECT 1 FROM reviews WHERE reviews.word = cards.word)",
+        params![word],
+    )?;
+    Ok(())
+}
+
+fn card_from_row(row: &rusqlite::Row) -> rusqlite::Result<StudyCard> {
+    Ok(StudyCard {
+        word: row.get(0)?,
+        reading: row.get(1)?,
+        translation: row.get(2)?,
+        sentence: row.get(3)?,
+        sentence_translation: row.get(4)?,
+        ease: row.get(5)?,
+        interval_days: row.get(6)?,
+        repetitions: row.get(7)?,
+        lapses: row.get(8)?,
+        due: row.get(9)?,
+    })
+}
+
+pub fn due_cards(connection: &Connection, now: u64) -> rusqlite::Result<Vec<StudyCard>> {
+    let mut statement = connection.prepare("SELECT * FROM cards WHERE due <= ?1 ORDER BY due")?;
+    let cards = statement.query_map(params![now], card_from_row)?;
+    cards.collect()
+}
+
+pub fn due_count(connection: &Connection, now: u64) -> rusqlite::Result<usize> {
+    connection.query_row(
+        "SELECT COUNT(*) FROM cards WHERE due <= ?1",
+        params![now],
+        |row| row.get(0),
+    )
+}
+
+// Schedules the next review of a word and records the grade it got.
+pub fn review(
+    connection: &Connection,
+    word: &str,
+    grade: Grade,
+    now: u64,
+) -> rusqlite::Result<Option<StudyCard>> {
+    let card = connection
+        .query_row(
+            "SELECT * FROM cards WHERE word = ?1",
+            params![word],
+            card_from_row,
+        )
+        .optional()?;
+    let mut card = match card {
+        Some(card) => card,
+        None => return Ok(None),
+    };
+    schedule(&mut card, grade, now);
+    connection.execute(
+        "UPDATE cards SET ease = ?2, interval_days = ?3, repetitions = ?4, lapses = ?5, due = ?6
+         WHERE word = ?1",
+        params![
+            card.word,
+            card.ease,
+            card.interval_days,
+            card.repetitions,
+            card.lapses,
+            card.due
+        ],
+    )?;
+    connection.execute(
+        "INSERT INTO reviews VALUES (?1, ?2, ?3)",
+        params![card.word, now, grade.label()],
+    )?;
+    Ok(Some(card))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card() -> StudyCard {
+        StudyCard::new(
+            "猫".to_string(),
+            "ねこ".to_string(),
+            "cat".to_string(),
+            "猫が好き。".to_string(),
+            "I like cats.".to_string(),
+            0,
+        )
+    }
+
+    #[test]
+    fn test_sm2_intervals() {
+        let mut card = card();
+        schedule(&mut card, Grade::Good, 0);
+        assert_eq!(card.interval_days, 1);
+        schedule(&mut card, Grade::Good, 0);
+        assert_eq!(card.interval_days, 6);
+        schedule(&mut card, Grade::Good, 0);
+        assert_eq!(card.interval_days, 15);
+        assert_eq!(card.due, 15 * DAY);
+        assert!((card.ease - 2.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_sm2_lapse_resets_and_keeps_ease() {
+        let mut card = card();
+        schedule(&mut card, Grade::Easy, 0);
+        assert!((card.ease - 2.6).abs() < 1e-9);
+        schedule(&mut card, Grade::Again, 100);
+        assert_eq!(card.repetitions, 0);
+        assert_eq!(card.lapses, 1);
+        assert_eq!(card.due, 100 + 10 * MINUTE);
+        assert!((card.ease - 2.6).abs() < 1e-9);
+        schedule(&mut card, Grade::Good, 200);
+        assert_eq!(card.interval_days, 1);
+    }
+
+    #[test]
+    fn test_sm2_ease_floor() {
+        let mut card = card();
+        for _ in 0..10 {
+            schedule(&mut card, Grade::Hard, 0);
+        }
+        assert!((card.ease - 1.3).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_review_in_database() {
+        let connection = Connection::open_in_memory().unwrap();
+        create_schema(&connection).unwrap();
+        add_card(&connection, &card()).unwrap();
+        // saving the same word again doesn't add a second card
+        add_card(&connection, &card()).unwrap();
+        assert_eq!(due_count(&connection, 0).unwrap(), 1);
+
+        let reviewed = review(&connection, "猫", Grade::Good, 0).unwrap().unwrap();
+        assert_eq!(reviewed.due, DAY);
+        assert_eq!(due_count(&connection, 0).unwrap(), 0);
+        assert_eq!(due_cards(&connection, DAY).unwrap(), vec![reviewed]);
+        assert_eq!(review(&connection, "犬", Grade::Good, 0).unwrap(), None);
+
+        // reviewed cards survive an undo
+        remove_new_card(&connection, "猫").unwrap();
+        assert_eq!(due_count(&connection, DAY).unwrap(), 1);
+    }
+}
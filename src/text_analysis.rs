@@ -0,0 +1,207 @@
+// Splits pasted Japanese text into words, to mine it for vocabulary. Words are found by
+// longest match against a dictionary, inflected ones through their dictionary form.
+use crate::deinflect;
+use crate::known_words::FUNCTION_WORDS;
+use crate::sentence_index::is_japanese;
+
+// longer words than this are rare enough to not look for
+const MAX_WORD_LENGTH: usize = 12;
+const SENTENCE_ENDS: [char; 6] = ['。', '！', '？', '!', '?', '\n'];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    // the text as it's written, e.g. 食べました
+    pub surface: String,
+    // its dictionary form, e.g. 食べる, None for text that isn't a word
+    pub word: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Analysis {
+    pub sentences: Vec<Vec<Token>>,
+}
+
+// A word of the text, the first sentence it's in is the example for its card.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VocabularyWord {
+    pub word: String,
+    // the forms it was written in
+    pub forms: Vec<String>,
+    pub count: usize,
+    pub sentence: usize,
+}
+
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut sentence = String::new();
+    for c in text.chars() {
+        if c != '\n' {
+            sentence.push(c);
+        }
+        if SENTENCE_ENDS.contains(&c) {
+            sentences.push(std::mem::take(&mut sentence));
+        }
+    }
+    sentences.push(sentence);
+    sentences
+        .into_iter()
+        .map(|sentence| sentence.trim().to_string())
+        .filter(|sentence| !sentence.is_empty())
+        .collect()
+}
+
+// The longest word starting at the beginning of the text, with its length in chars.
+fn longest_word(chars: &[char], is_word: &impl Fn(&str) -> bool) -> Option<(usize, String)> {
+    (1..=chars.len().min(MAX_WORD_LENGTH))
+        .rev()
+        .filter(|&length| chars[..length].iter().all(|&c| is_japanese(c)))
+        .find_map(|length| {
+            let surface: String = chars[..length].iter().collect();
+            deinflect::dictionary_form(&surface, is_word).map(|word| (length, word))
+        })
+}
+
+// Text that isn't a word is kept together, up to the next word.
+pub fn segment(sentence: &str, is_word: &impl Fn(&str) -> bool) -> Vec<Token> {
+    let chars: Vec<char> = sentence.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut position = 0;
+    while position < chars.len() {
+        match longest_word(&chars[position..], is_word) {
+            Some((length, word)) => {
+                tokens.push(Token {
+                    surface: chars[position..position + length].iter().collect(),
+                    word: Some(word),
+                });
+                position += length;
+            }
+            None => {
+                match tokens.last_mut() {
+                    Some(token) if token.word.is_none() => token.surface.push(chars[position]),
+                    _ => tokens.push(Token {
+                        surface: chars[position].to_string(),
+                        word: None,
+                    }),
+                }
+                position += 1;
+            }
+        }
+    }
+    tokens
+}
+
+pub fn analyze(text: &str, is_word: impl Fn(&str) -> bool) -> Analysis {
+    Analysis {
+        sentences: split_sentences(text)
+            .iter()
+            .map(|sentence| segment(sentence, &is_word))
+            .collect(),
+    }
+}
+
+impl Analysis {
+    pub fn sentence_text(&self, sentence: usize) -> String {
+        self.sentences
+            .get(sentence)
+            .map(|tokens| tokens.iter().map(|token| token.surface.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    // The words of the text in the order they first appear, particles left out.
+    pub fn vocabulary(&self) -> Vec<VocabularyWord> {
+        let mut vocabulary: Vec<VocabularyWord> = Vec::new();
+        for (n, tokens) in self.sentences.iter().enumerate() {
+            for token in tokens {
+                let Some(word) = &token.word else {
+                    continue;
+                };
+                if FUNCTION_WORDS.contains(&word.as_str()) {
+                    continue;
+                }
+                match vocabulary.iter_mut().find(|known| &known.word == word) {
+                    Some(known) => {
+                        known.count += 1;
+                        if !known.forms.contains(&token.surface) {
+                            known.forms.push(token.surface.clone());
+                        }
+                    }
+                    None => vocabulary.push(VocabularyWord {
+                        word: word.clone(),
+                        forms: vec![token.surface.clone()],
+                        count: 1,
+                        sentence: n,
+                    }),
+                }
+            }
+        }
+        vocabulary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(word: &str) -> bool {
+        [
+            "昨日",
+            "私",
+            "は",
+            "日本語",
+            "日本",
+            "語",
+            "勉強",
+            "する",
+            "を",
+            "寿司",
+            "食べる",
+            "美味しい",
+            "が",
+            "た",
+        ]
+        .contains(&word)
+    }
+
+    fn surfaces(tokens: &[Token]) -> Vec<&str> {
+        tokens.iter().map(|token| token.surface.as_str()).collect()
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(
+            split_sentences("猫です。犬？\nはい\n\n"),
+            vec!["猫です。", "犬？", "はい"]
+        );
+    }
+
+    #[test]
+    fn test_segment() {
+        let tokens = segment("私は日本語を勉強しました。", &dictionary);
+        assert_eq!(
+            surfaces(&tokens),
+            vec!["私", "は", "日本語", "を", "勉強", "しました", "。"]
+        );
+        assert_eq!(tokens[5].word.as_deref(), Some("する"));
+        assert_eq!(tokens[6].word, None);
+
+        // unknown text stays in one piece
+        let tokens = segment("ABC寿司", &dictionary);
+        assert_eq!(surfaces(&tokens), vec!["ABC", "寿司"]);
+    }
+
+    #[test]
+    fn test_vocabulary() {
+        let analysis = analyze(
+            "昨日寿司を食べました。寿司が美味しかった！寿司を食べた。",
+            dictionary,
+        );
+        assert_eq!(analysis.sentences.len(), 3);
+        assert_eq!(analysis.sentence_text(1), "寿司が美味しかった！");
+        let vocabulary = analysis.vocabulary();
+        let words: Vec<&str> = vocabulary.iter().map(|word| word.word.as_str()).collect();
+        assert_eq!(words, vec!["昨日", "寿司", "食べる", "美味しい"]);
+        assert_eq!(vocabulary[1].count, 3);
+        assert_eq!(vocabulary[2].forms, vec!["食べました", "食べた"]);
+        assert_eq!(vocabulary[3].sentence, 1);
+    }
+}